@@ -0,0 +1,196 @@
+use bevy::prelude::*;
+use bevy_matchbox::prelude::PeerId;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+use crate::{args::MAX_PLAYERS, game::MAP_SIZE, GameState};
+
+const WALL_COUNT: usize = 14;
+const MAX_WALL_SIZE: i32 = 4;
+/// Walls stay this far away from the center, where players spawn.
+const SPAWN_CLEARANCE: f32 = 5.;
+const WALL_COLOR: Color = Color::srgb(0.2, 0.2, 0.2);
+/// Where players can start a round, all of them inside the spawn clearance.
+const SPAWN_POINTS: [Vec2; 8] = [
+    Vec2::new(-3., 0.),
+    Vec2::new(3., 0.),
+    Vec2::new(0., 3.),
+    Vec2::new(0., -3.),
+    Vec2::new(-2., 2.),
+    Vec2::new(2., -2.),
+    Vec2::new(2., 2.),
+    Vec2::new(-2., -2.),
+];
+const _: () = assert!(SPAWN_POINTS.len() >= MAX_PLAYERS);
+
+/// Seed for everything random in a match. Every peer must end up with the
+/// same one.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MatchSeed(pub u64);
+
+impl MatchSeed {
+    /// Every peer knows who's playing, so the players' ids are a seed they
+    /// agree on without sending anything.
+    pub fn from_players(players: &[PeerId]) -> Self {
+        let mut players = players.to_vec();
+        players.sort();
+
+        Self(players.iter().fold(0, |seed, player| {
+            let id = player.0.as_u128();
+            seed.rotate_left(17) ^ id as u64 ^ (id >> 64) as u64
+        }))
+    }
+}
+
+/// Walls generated from the [`MatchSeed`]. They never move, so they're not
+/// part of the rollback state.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Arena {
+    pub walls: Vec<Rect>,
+}
+
+impl Arena {
+    pub fn generate(seed: MatchSeed) -> Self {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed.0);
+        let half_map = MAP_SIZE as i32 / 2;
+        let mut walls = Vec::with_capacity(WALL_COUNT);
+
+        // Walls snap to the grid, so they're built from whole numbers only.
+        while walls.len() < WALL_COUNT {
+            let size = IVec2::new(
+                rng.gen_range(1..=MAX_WALL_SIZE),
+                rng.gen_range(1..=MAX_WALL_SIZE),
+            );
+            let min = IVec2::new(
+                rng.gen_range(-half_map..half_map - size.x),
+                rng.gen_range(-half_map..half_map - size.y),
+            );
+            let wall = Rect::from_corners(min.as_vec2(), (min + size).as_vec2());
+
+            let clearance = Rect::from_center_half_size(Vec2::ZERO, Vec2::splat(SPAWN_CLEARANCE));
+            if wall.intersect(clearance).is_empty() {
+                walls.push(wall);
+            }
+        }
+
+        Self { walls }
+    }
+
+    /// Where each player starts the given round, by handle. Every peer picks the
+    /// same points, but they change from one round to the next.
+    pub fn spawn_points(seed: MatchSeed, round: u32, players: usize) -> Vec<Vec2> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed.0.wrapping_add(u64::from(round) + 1));
+        let mut points = SPAWN_POINTS;
+        points.shuffle(&mut rng);
+        points[..players].to_vec()
+    }
+
+    /// Whether a square centered on `pos` overlaps a wall. Touching is fine.
+    pub fn blocks(&self, pos: Vec2, half_size: f32) -> bool {
+        self.walls.iter().any(|wall| {
+            let distance = (pos - wall.center()).abs();
+            let reach = wall.half_size() + half_size;
+            distance.x < reach.x && distance.y < reach.y
+        })
+    }
+}
+
+/// Draws the walls of the [`Arena`] for the current match.
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), spawn_walls);
+    }
+}
+
+/// Seeds a new match. Run when its session starts, so the arena exists before
+/// the first rollback frame.
+pub fn start_match(commands: &mut Commands, seed: MatchSeed) {
+    info!("Starting a match with seed {}", seed.0);
+    commands.insert_resource(Arena::generate(seed));
+    commands.insert_resource(seed);
+}
+
+fn spawn_walls(mut commands: Commands, arena: Res<Arena>) {
+    for wall in &arena.walls {
+        commands.spawn((
+            StateScoped(GameState::InGame),
+            Transform::from_translation(wall.center().extend(50.)),
+            Sprite {
+                color: WALL_COLOR,
+                custom_size: Some(wall.size()),
+                ..default()
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_arena() {
+        assert_eq!(
+            Arena::generate(MatchSeed(42)),
+            Arena::generate(MatchSeed(42))
+        );
+        assert_ne!(
+            Arena::generate(MatchSeed(42)),
+            Arena::generate(MatchSeed(43))
+        );
+    }
+
+    #[test]
+    fn walls_leave_room_to_spawn() {
+        for seed in 0..100 {
+            let arena = Arena::generate(MatchSeed(seed));
+            assert_eq!(arena.walls.len(), WALL_COUNT);
+            assert!(!arena.blocks(Vec2::ZERO, SPAWN_CLEARANCE));
+
+            let limit = MAP_SIZE as f32 / 2.;
+            assert!(arena.walls.iter().all(|wall| {
+                wall.min.cmpge(Vec2::splat(-limit)).all()
+                    && wall.max.cmple(Vec2::splat(limit)).all()
+            }));
+        }
+    }
+
+    #[test]
+    fn spawn_points_change_every_round() {
+        let seed = MatchSeed(7);
+        let rounds: Vec<_> = (0..10)
+            .map(|round| Arena::spawn_points(seed, round, MAX_PLAYERS))
+            .collect();
+
+        for (round, points) in rounds.iter().enumerate() {
+            assert_eq!(
+                *points,
+                Arena::spawn_points(seed, round as u32, MAX_PLAYERS)
+            );
+            assert!(points
+                .iter()
+                .enumerate()
+                .all(|(i, point)| !points[..i].contains(point)));
+            assert!(points
+                .iter()
+                .all(|point| !Arena::generate(seed).blocks(*point, 0.5)));
+        }
+        assert!(rounds.windows(2).any(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn seed_ignores_peer_order() {
+        let players = [
+            PeerId(uuid::Uuid::from_u128(1)),
+            PeerId(uuid::Uuid::from_u128(2 << 70)),
+        ];
+        let reversed = [players[1], players[0]];
+
+        assert_eq!(
+            MatchSeed::from_players(&players),
+            MatchSeed::from_players(&reversed)
+        );
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::prelude::*;
+
+use crate::{
+    input::MAX_LOCAL_PLAYERS,
+    rules::{Ruleset, DEFAULT_KILL_TARGET},
+};
+
+pub const MIN_PLAYERS: usize = 2;
+pub const MAX_PLAYERS: usize = 4;
+
+/// How to find the other players, read from the command line, environment
+/// variables or, on the web, the page's query string.
+///
+/// | Argument          | Environment variable       | Query parameter |
+/// |-------------------|----------------------------|-----------------|
+/// | `--matchbox URL`  | `EXTREME_BEVY_MATCHBOX`    | `matchbox`      |
+/// | `--room CODE`     | `EXTREME_BEVY_ROOM`        | `room`          |
+/// | `--players N`     | `EXTREME_BEVY_PLAYERS`     | `players`       |
+/// | `--input-delay N` | `EXTREME_BEVY_INPUT_DELAY` | `input_delay`   |
+/// | `--mode MODE`     | `EXTREME_BEVY_MODE`        | `mode`          |
+/// | `--follow PLAYER` | `EXTREME_BEVY_FOLLOW`      | `follow`        |
+/// | `--replay FILE`   | `EXTREME_BEVY_REPLAY`      |                 |
+/// | `--rules RULES`   | `EXTREME_BEVY_RULES`       | `rules`         |
+/// | `--kills N`       | `EXTREME_BEVY_KILLS`       | `kills`         |
+///
+/// `--synctest`, `--local` and `--spectate` are short for the matching `--mode`,
+/// `--replay` implies the replay mode and `--kills` the kills rules, unless
+/// `--rules` picks others. Arguments take precedence over environment
+/// variables.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    /// Address of the matchbox server, without a room.
+    pub matchbox: String,
+    /// Players using the same code end up in the same lobby.
+    pub room: Option<String>,
+    pub players: usize,
+    pub input_delay: usize,
+    pub mode: Mode,
+    /// Handle of the player a spectator's camera starts on, `None` for a
+    /// free camera.
+    pub follow: Option<usize>,
+    /// Replay file to watch.
+    pub replay: Option<String>,
+    /// The rules to play by. Spectators and replays use the ones of the match
+    /// they watch instead.
+    pub ruleset: Ruleset,
+    /// Kills needed to win when the menu switches to [`Ruleset::Kills`].
+    pub kill_target: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Play against others through the matchbox server.
+    #[default]
+    Online,
+    /// Every player is local and GGRS keeps rolling back and resimulating
+    /// frames to check that the game is deterministic.
+    SyncTest,
+    /// Two players sharing one keyboard, without any networking.
+    Local,
+    /// Watch a match in a room without taking part.
+    Spectate,
+    /// Watch a recorded match again.
+    Replay,
+}
+
+impl Mode {
+    pub fn is_online(&self) -> bool {
+        matches!(self, Mode::Online | Mode::Spectate)
+    }
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            matchbox: "ws://localhost:3536".to_string(),
+            room: None,
+            players: MIN_PLAYERS,
+            input_delay: 2,
+            mode: Mode::Online,
+            follow: Some(0),
+            replay: None,
+            ruleset: Ruleset::default(),
+            kill_target: DEFAULT_KILL_TARGET,
+        }
+    }
+}
+
+impl Args {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_command_line(
+            |name| std::env::var(format!("EXTREME_BEVY_{name}")).ok(),
+            std::env::args().skip(1),
+        )
+    }
+
+    /// Reads the `arguments`, falling back to what `var` returns for the
+    /// environment variable without its prefix.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn from_command_line(
+        var: impl Fn(&str) -> Option<String>,
+        mut args: impl Iterator<Item = String>,
+    ) -> Result<Self, String> {
+        let mut options = vec![
+            ("matchbox", var("MATCHBOX")),
+            ("room", var("ROOM")),
+            ("players", var("PLAYERS")),
+            ("input_delay", var("INPUT_DELAY")),
+            ("mode", var("MODE")),
+            ("follow", var("FOLLOW")),
+            ("replay", var("REPLAY")),
+            ("rules", var("RULES")),
+            ("kills", var("KILLS")),
+        ];
+
+        let mut from_arguments = Vec::new();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument `{arg}`"));
+            };
+            let (name, new_value) = match name {
+                "synctest" | "local" | "spectate" => ("mode".to_string(), name.to_string()),
+                _ => (
+                    name.replace('-', "_"),
+                    args.next().ok_or(format!("`{arg}` needs a value"))?,
+                ),
+            };
+            let Some((_, value)) = options.iter_mut().find(|(option, _)| *option == name) else {
+                return Err(format!("unknown option `{arg}`"));
+            };
+            *value = Some(new_value);
+            from_arguments.push(name);
+        }
+
+        // `--kills` implies the kills rules, which beats rules picked by the
+        // environment.
+        let from_arguments = |name: &str| from_arguments.iter().any(|option| option == name);
+        if from_arguments("kills") && !from_arguments("rules") {
+            options.retain(|(name, _)| *name != "rules");
+        }
+
+        Self::from_options(options)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_env() -> Result<Self, String> {
+        let search = web_sys::window()
+            .and_then(|window| window.location().search().ok())
+            .unwrap_or_default();
+        let params = web_sys::UrlSearchParams::new_with_str(&search)
+            .map_err(|_| format!("invalid query string `{search}`"))?;
+
+        Self::from_options(
+            [
+                "matchbox",
+                "room",
+                "players",
+                "input_delay",
+                "mode",
+                "follow",
+                "rules",
+                "kills",
+            ]
+            .map(|name| (name, params.get(name)))
+            .into(),
+        )
+    }
+
+    fn from_options(options: Vec<(&str, Option<String>)>) -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut rules = None;
+        let mut kills = None;
+
+        for (name, value) in options {
+            let Some(value) = value else {
+                continue;
+            };
+            match name {
+                "matchbox" => args.matchbox = value.trim_end_matches('/').to_string(),
+                "room" => args.room = Some(value).filter(|room| !room.is_empty()),
+                "players" => args.players = parse_number(name, &value)?,
+                "input_delay" => args.input_delay = parse_number(name, &value)?,
+                "mode" => {
+                    args.mode = match value.as_str() {
+                        "online" => Mode::Online,
+                        "synctest" => Mode::SyncTest,
+                        "local" => Mode::Local,
+                        "spectate" => Mode::Spectate,
+                        _ => return Err(format!("unknown mode `{value}`")),
+                    }
+                }
+                "follow" => {
+                    args.follow = match value.as_str() {
+                        "free" => None,
+                        _ => Some(parse_number(name, &value)?),
+                    }
+                }
+                "replay" => {
+                    args.mode = Mode::Replay;
+                    args.replay = Some(value);
+                }
+                "rules" => {
+                    rules =
+                        Some(Ruleset::from_name(&value).ok_or(format!("unknown rules `{value}`"))?)
+                }
+                "kills" => {
+                    let target = u32::try_from(parse_number(name, &value)?)
+                        .map_err(|_| format!("kills must be at most {}, got {value}", u32::MAX))?;
+                    if target == 0 {
+                        return Err("kills must be at least 1".to_string());
+                    }
+                    kills = Some(target);
+                }
+                _ => unreachable!("unknown option {name}"),
+            }
+        }
+
+        // Whichever order they came in, `--kills` only picks the rules when
+        // `--rules` doesn't.
+        args.kill_target = kills.unwrap_or(DEFAULT_KILL_TARGET);
+        args.ruleset = match rules {
+            Some(Ruleset::Kills { .. }) => Ruleset::Kills {
+                target: args.kill_target,
+            },
+            Some(ruleset) => ruleset,
+            None if kills.is_some() => Ruleset::Kills {
+                target: args.kill_target,
+            },
+            None => Ruleset::default(),
+        };
+
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&args.players) {
+            return Err(format!(
+                "players must be between {MIN_PLAYERS} and {MAX_PLAYERS}, got {}",
+                args.players
+            ));
+        }
+        if args.mode == Mode::Local && args.players > MAX_LOCAL_PLAYERS {
+            return Err(format!(
+                "only {MAX_LOCAL_PLAYERS} players can share a keyboard, got {}",
+                args.players
+            ));
+        }
+        // Player numbers start at 1.
+        if let Some(follow) = args.follow.filter(|_| args.mode == Mode::Spectate) {
+            if !(1..=args.players).contains(&follow) {
+                return Err(format!(
+                    "can only follow players 1 to {}, got {follow}",
+                    args.players
+                ));
+            }
+        }
+        args.follow = args.follow.map(|follow| follow.saturating_sub(1));
+        // Public lobbies fill up with players only, see `room_url`.
+        if args.mode == Mode::Spectate && args.room.is_none() {
+            return Err("spectators need the room code of the match".to_string());
+        }
+        if let Some(room) = &args.room {
+            if !room
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "room codes may only contain letters, digits, `-` and `_`, got `{room}`"
+                ));
+            }
+        }
+
+        Ok(args)
+    }
+
+    /// The matchbox room to join.
+    ///
+    /// Without a room code, the server puts every `players` peers that join
+    /// with the same rules into a lobby of their own. Everyone using a room
+    /// code shares one lobby instead, so spectators can join it too, and the
+    /// lobby checks that the players agree on the rules.
+    pub fn room_url(&self) -> String {
+        match (&self.room, self.ruleset) {
+            (Some(room), _) => format!("{}/extreme_bevy_{room}", self.matchbox),
+            (None, Ruleset::Rounds) => {
+                format!("{}/extreme_bevy?next={}", self.matchbox, self.players)
+            }
+            (None, ruleset) => format!(
+                "{}/extreme_bevy-{}?next={}",
+                self.matchbox,
+                ruleset.id(),
+                self.players
+            ),
+        }
+    }
+}
+
+fn parse_number(name: &str, value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{name} must be a number, got `{value}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn room_codes_get_their_own_lobby() {
+        let args = Args::from_options(vec![
+            ("matchbox", Some("wss://example.com/".to_string())),
+            ("room", Some("friday-night".to_string())),
+            ("players", Some("3".to_string())),
+            ("input_delay", None),
+        ])
+        .unwrap();
+
+        assert_eq!(args.input_delay, Args::default().input_delay);
+        assert_eq!(args.mode, Mode::Online);
+        assert_eq!(
+            args.room_url(),
+            "wss://example.com/extreme_bevy_friday-night"
+        );
+        assert_eq!(
+            Args::default().room_url(),
+            "ws://localhost:3536/extreme_bevy?next=2"
+        );
+    }
+
+    #[test]
+    fn offline_modes() {
+        for (value, mode) in [("synctest", Mode::SyncTest), ("local", Mode::Local)] {
+            let args = Args::from_options(vec![("mode", Some(value.to_string()))]).unwrap();
+            assert_eq!(args.mode, mode);
+            assert!(!args.mode.is_online());
+        }
+    }
+
+    #[test]
+    fn replays_are_watched_offline() {
+        let args =
+            Args::from_options(vec![("replay", Some("replays/final.replay".to_string()))]).unwrap();
+        assert_eq!(args.mode, Mode::Replay);
+        assert_eq!(args.replay.as_deref(), Some("replays/final.replay"));
+        assert!(!args.mode.is_online());
+    }
+
+    #[test]
+    fn rules_get_their_own_public_lobby() {
+        let args = Args::from_options(vec![("rules", Some("capture".to_string()))]).unwrap();
+        assert_eq!(args.ruleset, Ruleset::Capture);
+        assert_eq!(
+            args.room_url(),
+            "ws://localhost:3536/extreme_bevy-capture?next=2"
+        );
+
+        let args = Args::from_options(vec![("kills", Some("5".to_string()))]).unwrap();
+        assert_eq!(args.ruleset, Ruleset::Kills { target: 5 });
+        assert_eq!(
+            args.room_url(),
+            "ws://localhost:3536/extreme_bevy-kills-5?next=2"
+        );
+    }
+
+    #[test]
+    fn arguments_beat_the_environment() {
+        let parse = |vars: &[(&str, &str)], arguments: &[&str]| {
+            Args::from_command_line(
+                |name| {
+                    vars.iter()
+                        .find(|(var, _)| *var == name)
+                        .map(|(_, value)| value.to_string())
+                },
+                arguments.iter().map(|arg| arg.to_string()),
+            )
+            .unwrap()
+        };
+
+        let args = parse(&[("KILLS", "5")], &["--rules", "capture"]);
+        assert_eq!(args.ruleset, Ruleset::Capture);
+        // The menu still switches to the kills target asked for.
+        assert_eq!(args.ruleset.next(args.kill_target), Ruleset::Rounds);
+        assert_eq!(
+            Ruleset::FreeForAll.next(args.kill_target),
+            Ruleset::Kills { target: 5 }
+        );
+
+        let args = parse(&[("RULES", "capture")], &["--kills", "5"]);
+        assert_eq!(args.ruleset, Ruleset::Kills { target: 5 });
+
+        let args = parse(&[("KILLS", "5")], &["--rules", "kills"]);
+        assert_eq!(args.ruleset, Ruleset::Kills { target: 5 });
+
+        let args = parse(&[("RULES", "ffa"), ("PLAYERS", "4")], &["--players", "3"]);
+        assert_eq!(args.ruleset, Ruleset::FreeForAll);
+        assert_eq!(args.players, 3);
+    }
+
+    #[test]
+    fn spectators_follow_player_numbers() {
+        let spectate = |follow: &str| {
+            Args::from_options(vec![
+                ("room", Some("cup".to_string())),
+                ("mode", Some("spectate".to_string())),
+                ("follow", Some(follow.to_string())),
+            ])
+        };
+
+        let args = spectate("2").unwrap();
+        assert!(args.mode.is_online());
+        assert_eq!(args.follow, Some(1));
+        assert_eq!(spectate("free").unwrap().follow, None);
+        assert!(spectate("0").is_err());
+        assert!(spectate("3").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        for (name, value) in [
+            ("players", "1"),
+            ("players", "5"),
+            ("input_delay", "soon"),
+            ("room", "../admin"),
+            ("mode", "lan"),
+            ("mode", "spectate"),
+            ("rules", "ctf"),
+            ("kills", "0"),
+            ("kills", "5000000000"),
+        ] {
+            assert!(Args::from_options(vec![(name, Some(value.to_string()))]).is_err());
+        }
+
+        // There are only two sets of keys to share.
+        assert!(Args::from_options(vec![
+            ("players", Some("3".to_string())),
+            ("mode", Some("local".to_string())),
+        ])
+        .is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy)]
 pub struct Player {
     pub handle: usize,
 }
@@ -8,8 +8,15 @@ pub struct Player {
 #[derive(Component, Clone, Copy)]
 pub struct BulletReady(pub bool);
 
-#[derive(Component)]
-pub struct Bullet;
+#[derive(Component, Clone, Copy)]
+pub struct Bullet {
+    /// Handle of the player who fired it.
+    pub shooter: usize,
+}
+
+/// Rollback frames left before the bullet disappears.
+#[derive(Component, Clone, Copy)]
+pub struct BulletLifetime(pub u32);
 
 #[derive(Component, Clone, Copy)]
-pub struct MoveDir(pub Vec2);
\ No newline at end of file
+pub struct MoveDir(pub Vec2);
@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_ggrs::{ggrs::GgrsEvent, *};
+use bevy_matchbox::prelude::*;
+
+use crate::{Config, GameState};
+
+/// How long a notice about the connection stays on screen.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+/// How long the reason a match ended is shown before leaving it.
+const MATCH_OVER_DURATION: Duration = Duration::from_secs(5);
+
+#[derive(Component)]
+struct ConnectionText;
+
+/// What the GGRS session told us about the other peers during this match.
+#[derive(Resource, Default)]
+struct ConnectionStatus {
+    /// Peers whose connection dropped, but may still come back.
+    interrupted: HashSet<PeerId>,
+    /// Players who left, spectators leaving aren't counted.
+    disconnected: HashSet<PeerId>,
+    /// Something that just happened, shown for a little while.
+    notice: Option<(String, Timer)>,
+    /// Why the match can't go on, shown until it's time to leave.
+    match_over: Option<(String, Timer)>,
+}
+
+impl ConnectionStatus {
+    fn notify(&mut self, message: String) {
+        self.notice = Some((message, Timer::new(NOTICE_DURATION, TimerMode::Once)));
+    }
+
+    fn end_match(&mut self, reason: String) {
+        if self.match_over.is_none() {
+            info!("Match over: {reason}");
+            self.match_over = Some((reason, Timer::new(MATCH_OVER_DURATION, TimerMode::Once)));
+        }
+    }
+}
+
+/// Reacts to peers dropping out or desyncing during a match, telling the
+/// player what's going on and ending the match when it can't continue.
+pub struct ConnectionPlugin;
+
+impl Plugin for ConnectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), setup_connection_text)
+            .add_systems(
+                Update,
+                (
+                    handle_ggrs_events,
+                    leave_finished_match,
+                    leave_on_escape,
+                    update_connection_text,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+fn setup_connection_text(mut commands: Commands) {
+    commands.insert_resource(ConnectionStatus::default());
+    commands.spawn((
+        ConnectionText,
+        StateScoped(GameState::InGame),
+        Text::new(""),
+        TextFont {
+            font_size: 30.,
+            ..default()
+        },
+        Node {
+            align_self: AlignSelf::End,
+            justify_self: JustifySelf::Center,
+            margin: UiRect::bottom(Val::Px(10.)),
+            ..default()
+        },
+    ));
+}
+
+fn handle_ggrs_events(
+    session: Option<ResMut<Session<Config>>>,
+    mut status: ResMut<ConnectionStatus>,
+) {
+    let Some(mut session) = session else {
+        return;
+    };
+    // Spectators only talk to their host, without it there's nothing to watch.
+    let (events, players): (Vec<_>, _) = match session.as_mut() {
+        Session::P2P(session) => (session.events().collect(), session.num_players()),
+        Session::Spectator(session) => (session.events().collect(), 0),
+        Session::SyncTest(_) => return,
+    };
+
+    for event in events {
+        match event {
+            GgrsEvent::Disconnected { addr } => {
+                status.interrupted.remove(&addr);
+                // The host's spectators come after the players, and leaving
+                // doesn't change anything for anyone else.
+                if let Session::P2P(session) = session.as_ref() {
+                    if session
+                        .handles_by_address(addr)
+                        .iter()
+                        .all(|&handle| handle >= players)
+                    {
+                        info!("Spectator {addr} left");
+                        continue;
+                    }
+                }
+                warn!("{addr} disconnected");
+                status.disconnected.insert(addr);
+                // The match goes on as long as there's someone to play against.
+                if players.saturating_sub(status.disconnected.len()) < 2 {
+                    status.end_match(format!("{addr} left the match"));
+                } else {
+                    status.notify(format!("{addr} left the match"));
+                }
+            }
+            GgrsEvent::NetworkInterrupted {
+                addr,
+                disconnect_timeout,
+            } => {
+                warn!("Connection to {addr} interrupted, disconnecting in {disconnect_timeout}ms");
+                status.interrupted.insert(addr);
+            }
+            GgrsEvent::NetworkResumed { addr } => {
+                info!("Connection to {addr} resumed");
+                status.interrupted.remove(&addr);
+                status.notify(format!("{addr} is back"));
+            }
+            GgrsEvent::DesyncDetected {
+                frame,
+                local_checksum,
+                remote_checksum,
+                addr,
+            } => {
+                // Every frame after the first divergent one differs as well,
+                // only the first one is worth reporting.
+                if status.match_over.is_none() {
+                    error!(
+                        "Desync with {addr} on frame {frame}: \
+                         local checksum {local_checksum:X}, remote checksum {remote_checksum:X}"
+                    );
+                    status.end_match(format!("Out of sync with {addr}"));
+                }
+            }
+            GgrsEvent::WaitRecommendation { skip_frames } => {
+                debug!("Running {skip_frames} frames ahead of the other peers");
+                status.notify("Waiting for the other players to catch up".to_string());
+            }
+            GgrsEvent::Synchronizing { .. } | GgrsEvent::Synchronized { .. } => {}
+        }
+    }
+}
+
+fn leave_finished_match(
+    mut status: ResMut<ConnectionStatus>,
+    mut next_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+) {
+    if let Some((_, timer)) = &mut status.notice {
+        if timer.tick(time.delta()).finished() {
+            status.notice = None;
+        }
+    }
+
+    let Some((_, timer)) = &mut status.match_over else {
+        return;
+    };
+    if timer.tick(time.delta()).just_finished() {
+        next_state.set(GameState::Matchmaking);
+    }
+}
+
+/// Escape leaves the match for the main menu, whatever state it's in.
+fn leave_on_escape(keys: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        info!("Leaving the match");
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+fn update_connection_text(
+    status: Res<ConnectionStatus>,
+    session: Option<Res<Session<Config>>>,
+    mut texts: Query<&mut Text, With<ConnectionText>>,
+) {
+    let state = match session.as_deref() {
+        Some(Session::P2P(session)) => Some(session.current_state()),
+        Some(Session::Spectator(session)) => Some(session.current_state()),
+        _ => None,
+    };
+    let synchronizing = state == Some(ggrs::SessionState::Synchronizing);
+
+    let message = if let Some((reason, timer)) = &status.match_over {
+        let remaining = timer.remaining_secs().ceil();
+        format!("{reason}, back to matchmaking in {remaining}s")
+    } else if synchronizing {
+        "Synchronizing with the other players...".to_string()
+    } else if let Some(peer) = status.interrupted.iter().next() {
+        format!("Connection to {peer} interrupted...")
+    } else if let Some((notice, _)) = &status.notice {
+        notice.clone()
+    } else {
+        String::new()
+    };
+
+    for mut text in &mut texts {
+        text.0.clone_from(&message);
+    }
+}
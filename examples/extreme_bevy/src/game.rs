@@ -0,0 +1,597 @@
+use std::{collections::BTreeMap, hash::BuildHasher};
+
+use bevy::{
+    prelude::*,
+    utils::{FixedState, HashSet},
+};
+use bevy_ggrs::*;
+
+use crate::{
+    arena::{Arena, MatchSeed},
+    args::Args,
+    components::*,
+    rules::RulesPlugin,
+    sound::{start_sound_frame, SoundEffect, SoundRequests},
+    Config,
+};
+
+/// Rate of the rollback schedule. Everything in it advances by exactly one
+/// step of this rate per frame, regardless of the render frame rate.
+pub const FPS: usize = 60;
+const FRAME_SECONDS: f32 = 1. / FPS as f32;
+
+pub const MAP_SIZE: u32 = 41;
+const PLAYER_RADIUS: f32 = 0.5;
+const BULLET_RADIUS: f32 = 0.025;
+/// How long a bullet flies if it doesn't hit anything, in rollback frames.
+const BULLET_LIFETIME_FRAMES: u32 = 120;
+/// Length of the pause between two rounds, in rollback frames.
+pub const ROUND_END_FRAMES: u32 = 60;
+
+/// Where the current round is at. It is rolled back with the rest of the
+/// game, so every peer changes rounds on the same frame.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RoundState {
+    /// Players need to be (re)spawned.
+    #[default]
+    Starting,
+    InRound,
+    /// Someone died, waiting for the interlude to run out.
+    Ended {
+        frames_left: u32,
+    },
+}
+
+impl RoundState {
+    /// Ends the round, the next one starts after the interlude.
+    pub fn end(&mut self) {
+        *self = RoundState::Ended {
+            frames_left: ROUND_END_FRAMES,
+        };
+    }
+}
+
+/// Rounds started in this match, so each one can pick new spawn points.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Round(pub u32);
+
+/// Points, by player handle. What earns one depends on the ruleset.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Scores(pub BTreeMap<usize, u32>);
+
+/// The handle of the player who won the match, once someone did. The scores
+/// start over with the next round.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MatchWinner(pub Option<usize>);
+
+/// A player shot this frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub victim: usize,
+    pub shooter: usize,
+}
+
+/// Who got shot on the frame being simulated, for the rules to score. Every
+/// frame starts without any, so it doesn't need to be rolled back.
+#[derive(Resource, Default)]
+pub struct Hits(pub Vec<Hit>);
+
+/// Where rulesets decide who scores and when a round ends. It runs during
+/// rounds, once the frame's hits are known.
+#[derive(SystemSet, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RulesSet;
+
+/// Where [`RulesSet`] runs in the [`GgrsSchedule`].
+pub fn rules_set() -> impl IntoSystemSetConfigs {
+    RulesSet
+        .after(kill_players)
+        .before(round_end_timeout)
+        .run_if(resource_equals(RoundState::InRound))
+}
+
+/// The game itself: everything that's rolled back and how it advances each
+/// frame, played by the rules in [`Args`]. It needs a GGRS session, the
+/// [`Args`], a [`MatchSeed`] and an [`Arena`], but no window, so it also runs
+/// headless under `MinimalPlugins`.
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.set_rollback_schedule_fps(FPS)
+            // Entities despawned by a frame that gets rolled back are spawned
+            // again with only the components registered here, so the markers
+            // systems look for must be too.
+            .rollback_component_with_copy::<Player>()
+            .rollback_component_with_copy::<Bullet>()
+            .rollback_component_with_clone::<Transform>()
+            .rollback_component_with_copy::<BulletReady>()
+            .rollback_component_with_copy::<MoveDir>()
+            .rollback_component_with_copy::<BulletLifetime>()
+            .rollback_resource_with_copy::<RoundState>()
+            .rollback_resource_with_copy::<Round>()
+            .rollback_resource_with_clone::<Scores>()
+            .rollback_resource_with_copy::<MatchWinner>()
+            .checksum_component::<Transform>(checksum_transform)
+            .checksum_resource_with_hash::<RoundState>()
+            .checksum_resource_with_hash::<Round>()
+            .checksum_resource_with_hash::<Scores>()
+            .checksum_resource_with_hash::<MatchWinner>()
+            .init_resource::<RoundState>()
+            .init_resource::<Round>()
+            .init_resource::<Scores>()
+            .init_resource::<MatchWinner>()
+            .init_resource::<Hits>()
+            .init_resource::<SoundRequests>()
+            .add_plugins(RulesPlugin)
+            .configure_sets(GgrsSchedule, rules_set())
+            .add_systems(
+                GgrsSchedule,
+                (
+                    start_sound_frame,
+                    spawn_players.run_if(resource_equals(RoundState::Starting)),
+                    (
+                        move_players,
+                        reload_bullet,
+                        fire_bullets,
+                        move_bullet,
+                        despawn_bullets,
+                        kill_players,
+                    )
+                        .chain()
+                        .run_if(resource_equals(RoundState::InRound)),
+                    round_end_timeout,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Starts a new round, removing whatever is left of the previous one.
+#[allow(clippy::too_many_arguments)]
+fn spawn_players(
+    mut commands: Commands,
+    args: Res<Args>,
+    seed: Res<MatchSeed>,
+    mut round: ResMut<Round>,
+    mut round_state: ResMut<RoundState>,
+    mut scores: ResMut<Scores>,
+    mut winner: ResMut<MatchWinner>,
+    players: Query<Entity, With<Player>>,
+    bullets: Query<Entity, With<Bullet>>,
+) {
+    if winner.0.take().is_some() {
+        scores.0.clear();
+    }
+    for entity in players.iter().chain(&bullets) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let spawn_points = Arena::spawn_points(*seed, round.0, args.players);
+    round.0 += 1;
+
+    for (handle, pos) in spawn_points.into_iter().enumerate() {
+        // Facing away from the center.
+        let dir = pos.normalize();
+
+        commands
+            .spawn((
+                Player { handle },
+                BulletReady(true),
+                MoveDir(dir),
+                Transform::from_translation(pos.extend(100.)),
+            ))
+            .add_rollback();
+
+        scores.0.entry(handle).or_default();
+    }
+    *round_state = RoundState::InRound;
+}
+
+fn move_players(
+    inputs: Res<PlayerInputs<Config>>,
+    arena: Res<Arena>,
+    mut players: Query<(&mut Transform, &mut MoveDir, &Player)>,
+) {
+    for (mut transform, mut move_dir, player) in &mut players {
+        let (input, _) = inputs[player.handle];
+
+        let movement = input.movement();
+
+        if movement == Vec2::ZERO {
+            continue;
+        }
+
+        move_dir.0 = movement.normalize();
+
+        let new_pos = step_player(transform.translation.xy(), movement, &arena);
+
+        transform.translation.x = new_pos.x;
+        transform.translation.y = new_pos.y;
+    }
+}
+
+/// Where a player ends up after moving for one frame. Each axis is moved on
+/// its own, so players slide along walls instead of sticking to them.
+fn step_player(pos: Vec2, movement: Vec2, arena: &Arena) -> Vec2 {
+    let move_speed = 7.;
+    let move_delta = movement * move_speed * FRAME_SECONDS;
+    let limit = Vec2::splat(MAP_SIZE as f32 / 2. - PLAYER_RADIUS);
+
+    let mut new_pos = pos;
+    for axis in [Vec2::X, Vec2::Y] {
+        let candidate = (new_pos + move_delta * axis).clamp(-limit, limit);
+        if !arena.blocks(candidate, PLAYER_RADIUS) {
+            new_pos = candidate;
+        }
+    }
+    new_pos
+}
+
+fn fire_bullets(
+    mut commands: Commands,
+    inputs: Res<PlayerInputs<Config>>,
+    mut sounds: ResMut<SoundRequests>,
+    mut players: Query<(&Transform, &Player, &mut BulletReady, &MoveDir)>,
+) {
+    // Rollback entities are told apart by the order they're spawned in, which
+    // mustn't depend on the query order, it differs between peers.
+    let mut players: Vec<_> = players.iter_mut().collect();
+    players.sort_by_key(|(_, player, ..)| player.handle);
+
+    for (transform, player, mut bullet_ready, move_dir) in players {
+        // Spawn bullet
+        let (input, _) = inputs[player.handle];
+        if input.fire() && bullet_ready.0 {
+            let dir = input.aim().unwrap_or(move_dir.0);
+            // Spawn the bullet just outside the player so it doesn't hit them.
+            let player_pos = transform.translation.xy();
+            let pos = player_pos + dir * (PLAYER_RADIUS + BULLET_RADIUS);
+            commands
+                .spawn((
+                    Bullet {
+                        shooter: player.handle,
+                    },
+                    BulletLifetime(BULLET_LIFETIME_FRAMES),
+                    Transform::from_translation(pos.extend(200.))
+                        .with_rotation(Quat::from_rotation_arc_2d(Vec2::X, dir)),
+                    MoveDir(dir),
+                ))
+                .add_rollback();
+            bullet_ready.0 = false;
+            sounds.play(SoundEffect::Shot);
+        }
+    }
+}
+
+fn reload_bullet(
+    inputs: Res<PlayerInputs<Config>>,
+    mut players: Query<(&mut BulletReady, &Player)>,
+) {
+    for (mut can_fire, player) in players.iter_mut() {
+        let (input, _) = inputs[player.handle];
+        if !input.fire() {
+            can_fire.0 = true
+        }
+    }
+}
+
+fn move_bullet(mut bullets: Query<(&mut Transform, &MoveDir), With<Bullet>>) {
+    for (mut transform, dir) in &mut bullets {
+        let speed = 20.;
+        let delta = dir.0 * speed * FRAME_SECONDS;
+        transform.translation += delta.extend(0.);
+    }
+}
+
+/// Removes bullets that hit a wall, left the arena or flew for too long, so
+/// the rollback snapshots don't keep growing.
+fn despawn_bullets(
+    mut commands: Commands,
+    arena: Res<Arena>,
+    mut bullets: Query<(Entity, &Transform, &mut BulletLifetime), With<Bullet>>,
+) {
+    let limit = MAP_SIZE as f32 / 2.;
+
+    for (entity, transform, mut lifetime) in &mut bullets {
+        lifetime.0 = lifetime.0.saturating_sub(1);
+        let pos = transform.translation.xy();
+
+        if lifetime.0 == 0
+            || pos.x.abs() > limit
+            || pos.y.abs() > limit
+            || arena.blocks(pos, BULLET_RADIUS)
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Removes the players that got shot, and the bullets that hit them, leaving
+/// it to the rules to decide what that means.
+fn kill_players(
+    mut commands: Commands,
+    mut hits: ResMut<Hits>,
+    mut sounds: ResMut<SoundRequests>,
+    players: Query<(Entity, &Player, &Transform)>,
+    bullets: Query<(Entity, &Bullet, &Transform)>,
+) {
+    // All hits are collected before anything is despawned, so the outcome
+    // doesn't depend on the order the queries iterate in on each peer.
+    let mut hit_players = HashSet::new();
+    let mut hit_bullets = HashSet::new();
+    // Shot by several bullets at once, the lowest handle gets the kill.
+    let mut shooters = BTreeMap::new();
+
+    for (player_entity, player, player_transform) in &players {
+        for (bullet_entity, bullet, bullet_transform) in &bullets {
+            let distance = player_transform
+                .translation
+                .xy()
+                .distance(bullet_transform.translation.xy());
+
+            if distance < PLAYER_RADIUS + BULLET_RADIUS {
+                hit_players.insert(player_entity);
+                hit_bullets.insert(bullet_entity);
+                shooters
+                    .entry(player.handle)
+                    .and_modify(|shooter: &mut usize| *shooter = (*shooter).min(bullet.shooter))
+                    .or_insert(bullet.shooter);
+            }
+        }
+    }
+
+    hits.0 = shooters
+        .into_iter()
+        .map(|(victim, shooter)| Hit { victim, shooter })
+        .collect();
+    for _ in &hits.0 {
+        sounds.play(SoundEffect::Hit);
+    }
+
+    for entity in hit_players.into_iter().chain(hit_bullets) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn round_end_timeout(mut round_state: ResMut<RoundState>) {
+    if let RoundState::Ended { frames_left } = *round_state {
+        *round_state = match frames_left {
+            0 => RoundState::Starting,
+            _ => RoundState::Ended {
+                frames_left: frames_left - 1,
+            },
+        };
+    }
+}
+
+/// Hashes the exact bits of the translation, so peers that drifted apart by
+/// even a rounding error are caught.
+fn checksum_transform(transform: &Transform) -> u64 {
+    FixedState.hash_one(transform.translation.to_array().map(f32::to_bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::score_survivors;
+
+    fn spawn_world(players: &[(usize, Vec2)], bullets: &[(Vec2, Vec2)]) -> World {
+        let mut world = World::new();
+        world.init_resource::<Args>();
+        world.insert_resource(RoundState::InRound);
+        world.insert_resource(MatchSeed(0));
+        world.init_resource::<Round>();
+        world.init_resource::<Scores>();
+        world.init_resource::<MatchWinner>();
+        world.init_resource::<Hits>();
+        world.init_resource::<SoundRequests>();
+        world.init_resource::<Arena>();
+
+        for &(handle, pos) in players {
+            world.spawn((
+                Player { handle },
+                Transform::from_translation(pos.extend(100.)),
+            ));
+        }
+        for &(pos, dir) in bullets {
+            world.spawn((
+                Bullet { shooter: 0 },
+                BulletLifetime(BULLET_LIFETIME_FRAMES),
+                MoveDir(dir),
+                Transform::from_translation(pos.extend(200.)),
+            ));
+        }
+
+        world
+    }
+
+    fn players_state(world: &mut World) -> Vec<(usize, Vec3)> {
+        let mut players: Vec<_> = world
+            .query::<(&Player, &Transform)>()
+            .iter(world)
+            .map(|(player, transform)| (player.handle, transform.translation))
+            .collect();
+        players.sort_by_key(|(handle, _)| *handle);
+        players
+    }
+
+    fn bullets_state(world: &mut World) -> Vec<Vec3> {
+        let mut bullets: Vec<_> = world
+            .query_filtered::<&Transform, With<Bullet>>()
+            .iter(world)
+            .map(|transform| transform.translation)
+            .collect();
+        bullets.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+        bullets
+    }
+
+    #[test]
+    fn hit_detection_is_deterministic() {
+        let players = [(0, Vec2::new(-2., 0.)), (1, Vec2::new(2., 0.))];
+        let bullets = [
+            (Vec2::new(-1., 0.), Vec2::X),
+            (Vec2::new(1., 0.), -Vec2::X),
+            (Vec2::new(0., 3.), Vec2::Y),
+        ];
+
+        // Spawning in reverse order on one peer changes the query iteration order.
+        let mut reversed_players = players;
+        reversed_players.reverse();
+        let mut reversed_bullets = bullets;
+        reversed_bullets.reverse();
+
+        let mut world_a = spawn_world(&players, &bullets);
+        let mut world_b = spawn_world(&reversed_players, &reversed_bullets);
+
+        // Systems keep per-world state, so each world gets its own schedule.
+        let mut schedules = [(); 2].map(|_| {
+            let mut schedule = Schedule::default();
+            schedule
+                .add_systems((move_bullet, despawn_bullets, kill_players, score_survivors).chain());
+            schedule
+        });
+
+        for _ in 0..30 {
+            for (world, schedule) in [&mut world_a, &mut world_b].into_iter().zip(&mut schedules) {
+                schedule.run(world);
+            }
+
+            assert_eq!(players_state(&mut world_a), players_state(&mut world_b));
+            assert_eq!(bullets_state(&mut world_a), bullets_state(&mut world_b));
+        }
+
+        // Both players were shot by the bullet heading their way.
+        assert!(players_state(&mut world_a).is_empty());
+        assert_eq!(bullets_state(&mut world_a).len(), 1);
+        // Nobody survived, so nobody scored.
+        assert!(world_a.resource::<Scores>().0.is_empty());
+    }
+
+    #[test]
+    fn round_restarts_after_a_kill() {
+        let mut world = spawn_world(&[], &[]);
+        world.insert_resource(RoundState::Starting);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                spawn_players.run_if(resource_equals(RoundState::Starting)),
+                (move_bullet, kill_players, score_survivors)
+                    .chain()
+                    .run_if(resource_equals(RoundState::InRound)),
+                round_end_timeout,
+            )
+                .chain(),
+        );
+
+        schedule.run(&mut world);
+        assert_eq!(*world.resource::<RoundState>(), RoundState::InRound);
+        assert_eq!(players_state(&mut world).len(), 2);
+
+        // Player 1 gets shot where they stand.
+        let (_, target) = players_state(&mut world)[0];
+        world.spawn((
+            Bullet { shooter: 1 },
+            MoveDir(Vec2::ZERO),
+            Transform::from_translation(target),
+        ));
+        schedule.run(&mut world);
+        assert_eq!(
+            *world.resource::<RoundState>(),
+            RoundState::Ended {
+                frames_left: ROUND_END_FRAMES - 1
+            }
+        );
+        assert_eq!(
+            world.resource::<Scores>().0,
+            BTreeMap::from([(0, 0), (1, 1)])
+        );
+
+        for _ in 0..ROUND_END_FRAMES + 1 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(*world.resource::<RoundState>(), RoundState::InRound);
+        assert_eq!(players_state(&mut world).len(), 2);
+        assert!(bullets_state(&mut world).is_empty());
+        assert_eq!(*world.resource::<Round>(), Round(2));
+        assert_eq!(
+            world.resource::<Scores>().0,
+            BTreeMap::from([(0, 0), (1, 1)])
+        );
+    }
+
+    #[test]
+    fn bullet_count_stays_bounded() {
+        let mut world = spawn_world(&[], &[]);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((move_bullet, despawn_bullets).chain());
+
+        // Some bullets fly out of the arena, the ones standing still expire.
+        let directions = [Vec2::X, -Vec2::Y, Vec2::ONE.normalize(), Vec2::ZERO];
+
+        for frame in 0..10_000 {
+            let dir = directions[frame % directions.len()];
+            world.spawn((
+                Bullet { shooter: 0 },
+                BulletLifetime(BULLET_LIFETIME_FRAMES),
+                MoveDir(dir),
+                Transform::from_translation(Vec3::new(0., 0., 200.)),
+            ));
+            schedule.run(&mut world);
+
+            assert!(bullets_state(&mut world).len() <= BULLET_LIFETIME_FRAMES as usize);
+        }
+
+        assert!(bullets_state(&mut world)
+            .iter()
+            .all(|pos| pos.x.abs() <= MAP_SIZE as f32 / 2. && pos.y.abs() <= MAP_SIZE as f32 / 2.));
+    }
+
+    #[test]
+    fn walls_stop_players() {
+        let arena = Arena {
+            walls: vec![Rect::new(1., -2., 2., 2.)],
+        };
+
+        let mut pos = Vec2::ZERO;
+        for _ in 0..60 {
+            pos = step_player(pos, Vec2::X, &arena);
+        }
+        assert!(pos.x <= 1. - PLAYER_RADIUS);
+
+        // Moving diagonally into the wall slides along it.
+        let slid = step_player(pos, Vec2::ONE.normalize(), &arena);
+        assert_eq!(slid.x, pos.x);
+        assert!(slid.y > pos.y);
+    }
+
+    #[test]
+    fn walls_stop_bullets() {
+        let mut world = spawn_world(&[], &[(Vec2::ZERO, Vec2::X)]);
+        world.insert_resource(Arena {
+            walls: vec![Rect::new(3., -1., 4., 1.)],
+        });
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems((move_bullet, despawn_bullets).chain());
+
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+        assert!(bullets_state(&mut world).is_empty());
+    }
+
+    #[test]
+    fn checksum_catches_tiny_drift() {
+        let transform = Transform::from_xyz(1., 2., 100.);
+        let mut drifted = transform;
+        drifted.translation.x += f32::EPSILON;
+
+        assert_eq!(
+            checksum_transform(&transform),
+            checksum_transform(&transform)
+        );
+        assert_ne!(checksum_transform(&transform), checksum_transform(&drifted));
+    }
+}
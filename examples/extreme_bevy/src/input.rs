@@ -1,63 +1,237 @@
-use crate::Config;
-use bevy::{prelude::*, utils::HashMap};
+use crate::{
+    args::{Args, Mode},
+    components::Player,
+    Config,
+};
+use bevy::{prelude::*, utils::HashMap, window::PrimaryWindow};
 use bevy_ggrs::*;
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
 
-const INPUT_UP: u8 = 1 << 0;
-const INPUT_DOWN: u8 = 1 << 1;
-const INPUT_LEFT: u8 = 1 << 2;
-const INPUT_RIGHT: u8 = 1 << 3;
-const INPUT_FIRE: u8 = 1 << 4;
+const BUTTON_FIRE: u8 = 1 << 0;
 
+/// Analog values are sent as whole numbers in `-AXIS_MAX..=AXIS_MAX`, so
+/// every peer turns them back into exactly the same floats.
+const AXIS_MAX: f32 = i8::MAX as f32;
+
+/// One player's input for one frame, as sent to the other peers.
+#[repr(C)]
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Pod, Zeroable, Serialize, Deserialize,
+)]
+pub struct PlayerInput {
+    move_x: i8,
+    move_y: i8,
+    /// Zero when the player isn't aiming, they fire where they're heading.
+    aim_x: i8,
+    aim_y: i8,
+    buttons: u8,
+}
+
+impl PlayerInput {
+    pub fn new(movement: Vec2, aim: Vec2, fire: bool) -> Self {
+        let [move_x, move_y] = quantize(movement.clamp_length_max(1.));
+        let [aim_x, aim_y] = quantize(aim.normalize_or_zero());
+
+        Self {
+            move_x,
+            move_y,
+            aim_x,
+            aim_y,
+            buttons: if fire { BUTTON_FIRE } else { 0 },
+        }
+    }
+
+    /// How far and where to move, with a length of at most 1.
+    pub fn movement(&self) -> Vec2 {
+        dequantize(self.move_x, self.move_y).clamp_length_max(1.)
+    }
+
+    pub fn aim(&self) -> Option<Vec2> {
+        dequantize(self.aim_x, self.aim_y).try_normalize()
+    }
+
+    pub fn fire(&self) -> bool {
+        self.buttons & BUTTON_FIRE != 0
+    }
+}
+
+fn quantize(value: Vec2) -> [i8; 2] {
+    (value * AXIS_MAX)
+        .round()
+        .clamp(Vec2::splat(-AXIS_MAX), Vec2::splat(AXIS_MAX))
+        .to_array()
+        .map(|axis| axis as i8)
+}
+
+fn dequantize(x: i8, y: i8) -> Vec2 {
+    Vec2::new(x as f32, y as f32) / AXIS_MAX
+}
+
+/// Keys for up, down, left, right and fire.
+type KeyBindings = [&'static [KeyCode]; 5];
+
+const ALL_KEYS: KeyBindings = [
+    &[KeyCode::ArrowUp, KeyCode::KeyW],
+    &[KeyCode::ArrowDown, KeyCode::KeyS],
+    &[KeyCode::ArrowLeft, KeyCode::KeyA],
+    &[KeyCode::ArrowRight, KeyCode::KeyD],
+    &[KeyCode::Space, KeyCode::Enter],
+];
+
+/// How many players can share one keyboard in the local mode.
+pub const MAX_LOCAL_PLAYERS: usize = SHARED_KEYS.len();
+
+/// When two players share a keyboard, each gets one half of it.
+const SHARED_KEYS: [KeyBindings; 2] = [
+    [
+        &[KeyCode::KeyW],
+        &[KeyCode::KeyS],
+        &[KeyCode::KeyA],
+        &[KeyCode::KeyD],
+        &[KeyCode::Space],
+    ],
+    [
+        &[KeyCode::ArrowUp],
+        &[KeyCode::ArrowDown],
+        &[KeyCode::ArrowLeft],
+        &[KeyCode::ArrowRight],
+        &[KeyCode::Enter],
+    ],
+];
+
+/// Reads the keyboard and, when there is one, a gamepad for every local
+/// player. The first one also aims with the mouse while holding its left
+/// button. Nothing is read while the window doesn't have the focus.
+#[allow(clippy::too_many_arguments)]
 pub fn read_local_inputs(
     mut commands: Commands,
     keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    players: Query<(&Player, &Transform)>,
     local_players: Res<LocalPlayers>,
+    args: Res<Args>,
 ) {
+    // Keys pressed while another window has the focus aren't meant for the
+    // game, so local players stand still until it's back.
+    if windows.get_single().is_ok_and(|window| !window.focused) {
+        let idle = local_players
+            .0
+            .iter()
+            .map(|&handle| (handle, PlayerInput::default()))
+            .collect();
+        commands.insert_resource(LocalInputs::<Config>(idle));
+        return;
+    }
+
     let mut local_inputs = HashMap::new();
 
-    for handle in &local_players.0 {
-        let mut input = 0u8;
+    for (i, handle) in local_players.0.iter().enumerate() {
+        let bindings = match args.mode {
+            Mode::Local => SHARED_KEYS.get(i),
+            _ => Some(&ALL_KEYS),
+        };
+        let pressed = |direction: usize| {
+            bindings.is_some_and(|bindings| keys.any_pressed(bindings[direction].iter().copied()))
+        };
 
-        if keys.any_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
-            input |= INPUT_UP;
+        let mut movement = Vec2::ZERO;
+        if pressed(0) {
+            movement.y += 1.;
+        }
+        if pressed(1) {
+            movement.y -= 1.;
         }
-        if keys.any_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
-            input |= INPUT_DOWN;
+        if pressed(2) {
+            movement.x -= 1.;
         }
-        if keys.any_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
-            input |= INPUT_LEFT;
+        if pressed(3) {
+            movement.x += 1.;
         }
-        if keys.any_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
-            input |= INPUT_RIGHT;
+        let mut movement = movement.normalize_or_zero();
+        let mut aim = Vec2::ZERO;
+        let mut fire = pressed(4);
+
+        if let Some(gamepad) = gamepads.iter().nth(i) {
+            if gamepad.left_stick() != Vec2::ZERO {
+                movement = gamepad.left_stick();
+            }
+            aim = gamepad.right_stick();
+            fire |= gamepad.any_pressed([GamepadButton::South, GamepadButton::RightTrigger2]);
         }
-        if keys.any_pressed([KeyCode::Space, KeyCode::Enter]) {
-            input |= INPUT_FIRE;
+
+        if i == 0 && mouse_buttons.pressed(MouseButton::Left) {
+            if let Some(cursor) = cursor_offset(&windows, &cameras, &players, *handle) {
+                aim = cursor;
+                fire = true;
+            }
         }
 
-        local_inputs.insert(*handle, input);
+        local_inputs.insert(*handle, PlayerInput::new(movement, aim, fire));
     }
 
     commands.insert_resource(LocalInputs::<Config>(local_inputs));
 }
 
-pub fn direction(input: u8) -> Vec2 {
-    let mut direction = Vec2::ZERO;
+/// Where the cursor is, relative to the given player.
+fn cursor_offset(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+    players: &Query<(&Player, &Transform)>,
+    handle: usize,
+) -> Option<Vec2> {
+    let cursor = windows.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = cameras.get_single().ok()?;
+    let cursor = camera.viewport_to_world_2d(camera_transform, cursor).ok()?;
+    let (_, transform) = players.iter().find(|(player, _)| player.handle == handle)?;
 
-    if input & INPUT_UP != 0 {
-        direction.y += 1.;
-    }
-    if input & INPUT_DOWN != 0 {
-        direction.y -= 1.;
-    }
-    if input & INPUT_RIGHT != 0 {
-        direction.x += 1.;
-    }
-    if input & INPUT_LEFT != 0 {
-        direction.x -= 1.;
-    }
-    direction.normalize_or_zero()
+    Some(cursor - transform.translation.xy())
 }
 
-pub fn fire(input: u8) -> bool {
-    input & INPUT_FIRE != 0
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialization_round_trip() {
+        let inputs = [
+            PlayerInput::default(),
+            PlayerInput::new(Vec2::new(0.5, -1.), Vec2::new(-3., 4.), true),
+            PlayerInput::new(Vec2::splat(10.), Vec2::ZERO, false),
+        ];
+
+        for input in inputs {
+            // GGRS sends inputs with bincode.
+            let bytes = bincode::serialize(&input).unwrap();
+            assert_eq!(bytes.len(), size_of::<PlayerInput>());
+            assert_eq!(bincode::deserialize::<PlayerInput>(&bytes).unwrap(), input);
+
+            assert_eq!(
+                *bytemuck::from_bytes::<PlayerInput>(bytemuck::bytes_of(&input)),
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn quantized_values_are_stable() {
+        let input = PlayerInput::new(Vec2::new(0.3, 0.4), Vec2::new(-3., 4.), true);
+
+        // Decoding and encoding again doesn't drift.
+        let aim = input.aim().unwrap();
+        assert_eq!(PlayerInput::new(input.movement(), aim, input.fire()), input);
+
+        assert!(input.fire());
+        assert!((input.movement() - Vec2::new(0.3, 0.4)).length() < 1. / AXIS_MAX);
+        assert!((aim - Vec2::new(-0.6, 0.8)).length() < 1. / AXIS_MAX);
+        assert!(input.movement().length() <= 1.);
+        assert_eq!(
+            PlayerInput::new(Vec2::X * 5., Vec2::ZERO, false).movement(),
+            Vec2::X
+        );
+        assert_eq!(PlayerInput::default().aim(), None);
+    }
 }
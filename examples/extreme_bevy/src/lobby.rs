@@ -0,0 +1,597 @@
+use std::time::Duration;
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_ggrs::*;
+use bevy_matchbox::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{self, MatchSeed},
+    args::{Args, Mode, MAX_PLAYERS, MIN_PLAYERS},
+    game::FPS,
+    rules::Ruleset,
+    Config, GameState,
+};
+
+/// Carries GGRS traffic once the game has started.
+const GGRS_CHANNEL: usize = 0;
+/// Carries lobby messages, which must not get lost. Each one is one of the
+/// `MSG_*` bytes, followed by the rules the peer wants to play by, or by a
+/// [`StartMessage`] for `MSG_START`.
+const LOBBY_CHANNEL: usize = 1;
+
+const MSG_NOT_READY: u8 = 0;
+const MSG_READY: u8 = 1;
+const MSG_SPECTATING: u8 = 2;
+const MSG_START: u8 = 3;
+
+/// Without a peer id by then, the signalling server is considered unreachable.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Lobbies that don't start a game by then go back to the main menu.
+const LOBBY_TIMEOUT: Duration = Duration::from_secs(120);
+
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.15);
+const BUTTON_HOVERED_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const BUTTON_PRESSED_COLOR: Color = Color::srgb(0.35, 0.75, 0.35);
+const ERROR_COLOR: Color = Color::srgb(1., 0.3, 0.3);
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuButton {
+    Play,
+    Rules,
+    Ready,
+    Back,
+}
+
+#[derive(Component)]
+struct LobbyText;
+
+#[derive(Component)]
+struct LobbyErrorText;
+
+#[derive(Component)]
+struct ReadyButtonText;
+
+#[derive(Component)]
+struct RulesButtonText;
+
+/// What the host sends everyone once all players are ready. Nobody starts
+/// before it arrives, so a player changing their mind at the last moment
+/// can't leave some peers in the lobby while the others are in-game.
+#[derive(Serialize, Deserialize, Debug)]
+struct StartMessage {
+    ruleset: Ruleset,
+    /// Sorted by id, which makes every peer agree on the handles. The first
+    /// one is the host.
+    players: Vec<PeerId>,
+    spectators: Vec<PeerId>,
+}
+
+/// State of the ready-up handshake for the lobby we're in.
+#[derive(Resource)]
+struct Lobby {
+    ready: bool,
+    ready_peers: HashSet<PeerId>,
+    spectators: HashSet<PeerId>,
+    /// The rules each peer wants to play by.
+    rulesets: HashMap<PeerId, Ruleset>,
+    timer: Timer,
+    error: Option<String>,
+}
+
+impl Default for Lobby {
+    fn default() -> Self {
+        Self {
+            ready: false,
+            ready_peers: HashSet::new(),
+            spectators: HashSet::new(),
+            rulesets: HashMap::new(),
+            timer: Timer::new(LOBBY_TIMEOUT, TimerMode::Once),
+            error: None,
+        }
+    }
+}
+
+/// Main menu and the lobby where online players meet and ready up before a
+/// match.
+pub struct LobbyPlugin;
+
+impl Plugin for LobbyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::MainMenu),
+            (close_matchbox_socket, setup_main_menu),
+        )
+        .add_systems(
+            OnEnter(GameState::Matchmaking),
+            (start_matchbox_socket, setup_lobby).run_if(is_online),
+        )
+        .add_systems(
+            Update,
+            (
+                button_colors,
+                handle_buttons,
+                (update_lobby, update_lobby_text)
+                    .chain()
+                    .run_if(in_state(GameState::Matchmaking).and(resource_exists::<Lobby>)),
+            ),
+        );
+    }
+}
+
+pub fn is_online(args: Res<Args>) -> bool {
+    args.mode.is_online()
+}
+
+fn setup_main_menu(mut commands: Commands, args: Res<Args>) {
+    let subtitle = match &args.room {
+        Some(room) => format!("{} players, room {room}", args.players),
+        None => format!("{} players", args.players),
+    };
+
+    commands
+        .spawn((StateScoped(GameState::MainMenu), menu_node()))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Extreme Bevy"),
+                TextFont {
+                    font_size: 60.,
+                    ..default()
+                },
+            ));
+            parent.spawn(Text::new(subtitle));
+            // Spectators and replays go by the rules of what they watch.
+            if !matches!(args.mode, Mode::Spectate | Mode::Replay) {
+                spawn_button(parent, MenuButton::Rules, &args.ruleset.to_string());
+            }
+            spawn_button(parent, MenuButton::Play, "Play");
+        });
+}
+
+fn setup_lobby(mut commands: Commands, args: Res<Args>) {
+    commands.insert_resource(Lobby::default());
+
+    commands
+        .spawn((StateScoped(GameState::Matchmaking), menu_node()))
+        .with_children(|parent| {
+            parent.spawn((LobbyText, Text::new("")));
+            parent.spawn((LobbyErrorText, Text::new(""), TextColor(ERROR_COLOR)));
+            // Spectators don't hold anyone up. Public lobbies are picked by
+            // the rules, so only players in a room can change them here.
+            if args.mode != Mode::Spectate {
+                if args.room.is_some() {
+                    spawn_button(parent, MenuButton::Rules, &args.ruleset.to_string());
+                }
+                spawn_button(parent, MenuButton::Ready, "Ready");
+            }
+            spawn_button(parent, MenuButton::Back, "Back");
+        });
+}
+
+fn menu_node() -> Node {
+    Node {
+        width: Val::Percent(100.),
+        height: Val::Percent(100.),
+        flex_direction: FlexDirection::Column,
+        align_items: AlignItems::Center,
+        justify_content: JustifyContent::Center,
+        row_gap: Val::Px(20.),
+        ..default()
+    }
+}
+
+fn spawn_button(parent: &mut ChildBuilder, button: MenuButton, label: &str) {
+    parent
+        .spawn((
+            button,
+            Button,
+            Node {
+                min_width: Val::Px(200.),
+                padding: UiRect::all(Val::Px(10.)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+        ))
+        .with_children(|parent| {
+            let mut label = parent.spawn(Text::new(label));
+            match button {
+                MenuButton::Ready => {
+                    label.insert(ReadyButtonText);
+                }
+                MenuButton::Rules => {
+                    label.insert(RulesButtonText);
+                }
+                _ => {}
+            }
+        });
+}
+
+fn button_colors(mut buttons: Query<(&Interaction, &mut BackgroundColor), Changed<Interaction>>) {
+    for (interaction, mut color) in &mut buttons {
+        color.0 = match interaction {
+            Interaction::Pressed => BUTTON_PRESSED_COLOR,
+            Interaction::Hovered => BUTTON_HOVERED_COLOR,
+            Interaction::None => BUTTON_COLOR,
+        };
+    }
+}
+
+fn handle_buttons(
+    buttons: Query<(&Interaction, &MenuButton), Changed<Interaction>>,
+    mut ready_texts: Query<&mut Text, With<ReadyButtonText>>,
+    mut rules_texts: Query<&mut Text, (With<RulesButtonText>, Without<ReadyButtonText>)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    lobby: Option<ResMut<Lobby>>,
+    socket: Option<ResMut<MatchboxSocket>>,
+    mut args: ResMut<Args>,
+) {
+    let Some((_, &button)) = buttons
+        .iter()
+        .find(|(interaction, _)| **interaction == Interaction::Pressed)
+    else {
+        return;
+    };
+
+    match button {
+        MenuButton::Play => next_state.set(GameState::Matchmaking),
+        MenuButton::Rules => {
+            args.ruleset = args.ruleset.next(args.kill_target);
+            for mut text in &mut rules_texts {
+                text.0 = args.ruleset.to_string();
+            }
+            // In the lobby, everyone checks again whether the rules agree.
+            if let (Some(lobby), Some(mut socket)) = (lobby, socket) {
+                send_to_everyone(&mut socket, lobby_message(&args, lobby.ready));
+            }
+        }
+        MenuButton::Back => next_state.set(GameState::MainMenu),
+        MenuButton::Ready => {
+            let (Some(mut lobby), Some(mut socket)) = (lobby, socket) else {
+                return;
+            };
+            lobby.ready = !lobby.ready;
+            send_to_everyone(&mut socket, lobby_message(&args, lobby.ready));
+            for mut text in &mut ready_texts {
+                text.0 = if lobby.ready { "Not ready" } else { "Ready" }.to_string();
+            }
+        }
+    }
+}
+
+fn start_matchbox_socket(mut commands: Commands, args: Res<Args>) {
+    let room_url = args.room_url();
+    info!("connecting to matchbox server: {room_url}");
+    let socket = WebRtcSocketBuilder::new(room_url)
+        .add_channel(ChannelConfig::unreliable())
+        .add_channel(ChannelConfig::reliable());
+    commands.insert_resource(MatchboxSocket::from(socket));
+}
+
+fn close_matchbox_socket(mut commands: Commands) {
+    commands.remove_resource::<MatchboxSocket>();
+    commands.remove_resource::<Lobby>();
+}
+
+/// What we tell the other peers about ourselves.
+fn lobby_message(args: &Args, ready: bool) -> Vec<u8> {
+    let status = match (args.mode, ready) {
+        (Mode::Spectate, _) => MSG_SPECTATING,
+        (_, true) => MSG_READY,
+        (_, false) => MSG_NOT_READY,
+    };
+    let mut message = vec![status];
+    message.extend(bincode::serialize(&args.ruleset).expect("rulesets always serialize"));
+    message
+}
+
+fn send_message(socket: &mut MatchboxSocket, peer: PeerId, message: Vec<u8>) {
+    socket
+        .channel_mut(LOBBY_CHANNEL)
+        .send(message.into_boxed_slice(), peer);
+}
+
+fn send_to_everyone(socket: &mut MatchboxSocket, message: Vec<u8>) {
+    let peers: Vec<_> = socket.connected_peers().collect();
+    for peer in peers {
+        send_message(socket, peer, message.clone());
+    }
+}
+
+fn update_lobby(
+    mut commands: Commands,
+    mut socket: ResMut<MatchboxSocket>,
+    mut lobby: ResMut<Lobby>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut args: ResMut<Args>,
+    time: Res<Time>,
+) {
+    if lobby.timer.tick(time.delta()).just_finished() {
+        warn!("Nobody started a game in time, back to the main menu");
+        next_state.set(GameState::MainMenu);
+        return;
+    }
+    if lobby.error.is_some() {
+        return;
+    }
+
+    let changes = match socket.try_update_peers() {
+        Ok(changes) => changes,
+        Err(err) => {
+            error!("Lost the matchbox server: {err}");
+            lobby.error = Some(format!("Lost connection to the matchbox server: {err}"));
+            return;
+        }
+    };
+    for (peer, state) in changes {
+        match state {
+            // Late joiners need to know who we are and whether we're ready.
+            PeerState::Connected => {
+                send_message(&mut socket, peer, lobby_message(&args, lobby.ready))
+            }
+            PeerState::Disconnected => {
+                lobby.ready_peers.remove(&peer);
+                lobby.spectators.remove(&peer);
+                lobby.rulesets.remove(&peer);
+            }
+        }
+    }
+
+    let mut start = None;
+    for (peer, packet) in socket.channel_mut(LOBBY_CHANNEL).receive() {
+        let Some((&kind, body)) = packet.split_first() else {
+            warn!("Ignoring empty lobby message from {peer}");
+            continue;
+        };
+        if kind == MSG_START {
+            match bincode::deserialize::<StartMessage>(body) {
+                Ok(message)
+                    if message.players.first() == Some(&peer)
+                        && (MIN_PLAYERS..=MAX_PLAYERS).contains(&message.players.len()) =>
+                {
+                    start = Some(message);
+                }
+                _ => warn!("Ignoring invalid start message from {peer}"),
+            }
+            continue;
+        }
+
+        match bincode::deserialize(body) {
+            Ok(ruleset) => {
+                lobby.rulesets.insert(peer, ruleset);
+            }
+            Err(_) => {
+                warn!("Ignoring lobby message without rules from {peer}");
+                continue;
+            }
+        }
+        match kind {
+            MSG_READY => {
+                lobby.ready_peers.insert(peer);
+            }
+            MSG_NOT_READY => {
+                lobby.ready_peers.remove(&peer);
+            }
+            MSG_SPECTATING => {
+                lobby.spectators.insert(peer);
+            }
+            _ => warn!("Ignoring unknown lobby message from {peer}"),
+        }
+    }
+
+    let Some(id) = socket.id() else {
+        if lobby.timer.elapsed() > CONNECT_TIMEOUT {
+            lobby.error = Some(format!(
+                "Could not reach the matchbox server at {}",
+                args.matchbox
+            ));
+        }
+        return;
+    };
+
+    let spectating = args.mode == Mode::Spectate;
+    if let Some(start) = start {
+        let invited = if spectating {
+            start.spectators.contains(&id)
+        } else {
+            start.players.contains(&id)
+        };
+        if !invited {
+            lobby.error = Some("The match started without you".to_string());
+            return;
+        }
+        enter_match(&mut commands, &mut socket, &mut args, id, start);
+        commands.remove_resource::<Lobby>();
+        next_state.set(GameState::InGame);
+        return;
+    }
+    // Spectators wait for the host to let them in.
+    if spectating {
+        return;
+    }
+
+    let mut players: Vec<_> = socket
+        .connected_peers()
+        .filter(|peer| !lobby.spectators.contains(peer))
+        .collect();
+    let everyone_ready = lobby.ready
+        && players.iter().all(|peer| {
+            lobby.ready_peers.contains(peer) && lobby.rulesets.get(peer) == Some(&args.ruleset)
+        });
+    players.push(id);
+    if players.len() != args.players || !everyone_ready {
+        return;
+    }
+
+    // The player with the lowest id hosts, the others wait for its start
+    // message.
+    players.sort();
+    if players[0] != id {
+        return;
+    }
+    let mut spectators: Vec<_> = lobby.spectators.iter().copied().collect();
+    spectators.sort();
+    let start = StartMessage {
+        ruleset: args.ruleset,
+        players,
+        spectators,
+    };
+
+    info!("All players are ready, starting the match");
+    let mut message = vec![MSG_START];
+    message.extend(bincode::serialize(&start).expect("start messages always serialize"));
+    send_to_everyone(&mut socket, message);
+    enter_match(&mut commands, &mut socket, &mut args, id, start);
+    commands.remove_resource::<Lobby>();
+    next_state.set(GameState::InGame);
+}
+
+/// Starts the session the host asked for, whether we're the host or not.
+fn enter_match(
+    commands: &mut Commands,
+    socket: &mut MatchboxSocket,
+    args: &mut Args,
+    id: PeerId,
+    start: StartMessage,
+) {
+    // Peers in a room may have asked for another player count.
+    args.players = start.players.len();
+    args.ruleset = start.ruleset;
+    info!("Going in-game: {} players, {}", args.players, args.ruleset);
+
+    arena::start_match(commands, MatchSeed::from_players(&start.players));
+    if args.mode == Mode::Spectate {
+        start_spectator_session(commands, socket, args, start.players[0]);
+    } else {
+        start_p2p_session(
+            commands,
+            socket,
+            args,
+            id,
+            &start.players,
+            &start.spectators,
+        );
+    }
+}
+
+fn start_p2p_session(
+    commands: &mut Commands,
+    socket: &mut MatchboxSocket,
+    args: &Args,
+    id: PeerId,
+    players: &[PeerId],
+    spectators: &[PeerId],
+) {
+    let mut session_builder = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(args.players)
+        .with_input_delay(args.input_delay)
+        .with_desync_detection_mode(ggrs::DesyncDetection::On { interval: 1 })
+        .with_fps(FPS)
+        .expect("invalid fps");
+
+    for (i, &player) in players.iter().enumerate() {
+        let player_type = if player == id {
+            ggrs::PlayerType::Local
+        } else {
+            ggrs::PlayerType::Remote(player)
+        };
+        session_builder = session_builder
+            .add_player(player_type, i)
+            .expect("failed to add player");
+    }
+
+    // The first player hosts the spectators, their handles come after the
+    // players'.
+    if players[0] == id {
+        for (i, &spectator) in spectators.iter().enumerate() {
+            session_builder = session_builder
+                .add_player(ggrs::PlayerType::Spectator(spectator), players.len() + i)
+                .expect("failed to add spectator");
+        }
+    }
+
+    let channel = socket.take_channel(GGRS_CHANNEL).unwrap();
+
+    let ggrs_session = session_builder
+        .start_p2p_session(channel)
+        .expect("failed to start session");
+
+    commands.insert_resource(Session::P2P(ggrs_session));
+}
+
+fn start_spectator_session(
+    commands: &mut Commands,
+    socket: &mut MatchboxSocket,
+    args: &Args,
+    host: PeerId,
+) {
+    let channel = socket.take_channel(GGRS_CHANNEL).unwrap();
+
+    let ggrs_session = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(args.players)
+        .with_fps(FPS)
+        .expect("invalid fps")
+        .start_spectator_session(host, channel)
+        .expect("failed to start spectator session");
+
+    commands.insert_resource(Session::Spectator(ggrs_session));
+}
+
+fn update_lobby_text(
+    mut socket: ResMut<MatchboxSocket>,
+    lobby: Res<Lobby>,
+    args: Res<Args>,
+    mut texts: Query<&mut Text, With<LobbyText>>,
+    mut error_texts: Query<&mut Text, (With<LobbyErrorText>, Without<LobbyText>)>,
+) {
+    let ready = |ready: bool| if ready { "ready" } else { "not ready" };
+    let room = args.room.as_deref().unwrap_or("public");
+
+    let mut lines = vec![];
+    match socket.id() {
+        None => lines.push(format!("Connecting to {}...", args.matchbox)),
+        Some(id) => {
+            let spectating = args.mode == Mode::Spectate;
+            let (spectators, players): (Vec<_>, Vec<_>) = socket
+                .connected_peers()
+                .partition(|peer| lobby.spectators.contains(peer));
+            lines.push(format!(
+                "Room {room}: {}/{} players, {} spectators",
+                players.len() + usize::from(!spectating),
+                args.players,
+                spectators.len() + usize::from(spectating),
+            ));
+            let you = if spectating {
+                "spectating".to_string()
+            } else {
+                format!("{}, {}", ready(lobby.ready), args.ruleset)
+            };
+            lines.push(format!("You ({id}): {you}"));
+            for peer in players {
+                let status = ready(lobby.ready_peers.contains(&peer));
+                match lobby.rulesets.get(&peer) {
+                    Some(ruleset) if spectating || *ruleset == args.ruleset => {
+                        lines.push(format!("{peer}: {status}, {ruleset}"))
+                    }
+                    Some(ruleset) => lines.push(format!("{peer}: {status}, wants {ruleset}")),
+                    None => lines.push(format!("{peer}: {status}")),
+                }
+            }
+            for peer in spectators {
+                lines.push(format!("{peer}: spectating"));
+            }
+        }
+    }
+    let remaining = lobby.timer.remaining_secs().ceil();
+    lines.push(format!("Back to the menu in {remaining}s"));
+
+    for mut text in &mut texts {
+        text.0 = lines.join("\n");
+    }
+    for mut text in &mut error_texts {
+        text.0 = lobby.error.clone().unwrap_or_default();
+    }
+}
@@ -0,0 +1,299 @@
+//! Plays the game between two headless apps connected in memory, to catch
+//! desyncs without a matchbox server or a window.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{prelude::*, time::TimeUpdateStrategy, utils::HashMap};
+use bevy_ggrs::{
+    ggrs::{DesyncDetection, GgrsEvent, Message, NonBlockingSocket},
+    *,
+};
+use bevy_matchbox::prelude::PeerId;
+
+use crate::{
+    arena::{Arena, MatchSeed},
+    args::Args,
+    components::*,
+    game::{GamePlugin, RoundState, Scores, FPS},
+    input::PlayerInput,
+    Config,
+};
+
+/// Messages waiting to be received, by recipient.
+type Network = Arc<Mutex<HashMap<PeerId, Vec<(PeerId, Message)>>>>;
+
+/// A GGRS socket that delivers every message instantly and in order to
+/// another socket of the same network.
+struct LoopbackSocket {
+    id: PeerId,
+    network: Network,
+}
+
+impl LoopbackSocket {
+    fn connect(ids: &[PeerId]) -> Vec<Self> {
+        let network = Network::default();
+        ids.iter()
+            .map(|&id| Self {
+                id,
+                network: network.clone(),
+            })
+            .collect()
+    }
+}
+
+impl NonBlockingSocket<PeerId> for LoopbackSocket {
+    fn send_to(&mut self, msg: &Message, addr: &PeerId) {
+        let mut network = self.network.lock().unwrap();
+        network
+            .entry(*addr)
+            .or_default()
+            .push((self.id, msg.clone()));
+    }
+
+    fn receive_all_messages(&mut self) -> Vec<(PeerId, Message)> {
+        let mut network = self.network.lock().unwrap();
+        network.remove(&self.id).unwrap_or_default()
+    }
+}
+
+/// The rolled back state after a frame, in an order that doesn't depend on
+/// the entities.
+#[derive(Clone, Debug, PartialEq)]
+struct Snapshot {
+    players: Vec<(usize, Vec3, Vec2, bool)>,
+    bullets: Vec<(Vec3, Vec2, u32)>,
+    round_state: RoundState,
+    scores: Scores,
+    /// Every rolled back entity, to catch any that lost their `Player` or
+    /// `Bullet` marker while being restored.
+    rollback_entities: usize,
+}
+
+/// The latest snapshot of every frame. Resimulated frames replace what was
+/// predicted before.
+#[derive(Resource, Default)]
+struct History(BTreeMap<i32, Snapshot>);
+
+fn take_snapshot(
+    frame: Res<RollbackFrameCount>,
+    players: Query<(&Player, &Transform, &MoveDir, &BulletReady)>,
+    bullets: Query<(&Transform, &MoveDir, &BulletLifetime), With<Bullet>>,
+    round_state: Res<RoundState>,
+    scores: Res<Scores>,
+    rollback_entities: Query<(), With<Rollback>>,
+    mut history: ResMut<History>,
+) {
+    let mut players: Vec<_> = players
+        .iter()
+        .map(|(player, transform, dir, ready)| {
+            (player.handle, transform.translation, dir.0, ready.0)
+        })
+        .collect();
+    players.sort_by_key(|(handle, ..)| *handle);
+
+    let mut bullets: Vec<_> = bullets
+        .iter()
+        .map(|(transform, dir, lifetime)| (transform.translation, dir.0, lifetime.0))
+        .collect();
+    bullets.sort_by(|(a, ..), (b, ..)| a.to_array().partial_cmp(&b.to_array()).unwrap());
+
+    history.0.insert(
+        frame.0,
+        Snapshot {
+            players,
+            bullets,
+            round_state: *round_state,
+            scores: scores.clone(),
+            rollback_entities: rollback_entities.iter().count(),
+        },
+    );
+}
+
+/// Each player walks in a small square for a second, then stands still and
+/// fires in bursts at the other one for a second. Their aim depends on where
+/// the other player is predicted to be, so predictions keep being wrong and
+/// kills happen on frames that get rolled back.
+fn scripted_input(handle: usize, frame: i32, aim: Option<Vec2>) -> PlayerInput {
+    const DIRECTIONS: [Vec2; 4] = [Vec2::X, Vec2::Y, Vec2::NEG_X, Vec2::NEG_Y];
+    let frame = frame as usize;
+
+    match aim {
+        Some(aim) if frame / 60 % 2 == 1 => PlayerInput::new(Vec2::ZERO, aim, frame % 16 < 8),
+        _ => PlayerInput::new(DIRECTIONS[(frame / 15 + handle) % 4], Vec2::ZERO, false),
+    }
+}
+
+fn read_scripted_inputs(
+    mut commands: Commands,
+    local_players: Res<LocalPlayers>,
+    frame: Res<RollbackFrameCount>,
+    players: Query<(&Player, &Transform)>,
+) {
+    let position = |wanted: &dyn Fn(usize) -> bool| {
+        players
+            .iter()
+            .find(|(player, _)| wanted(player.handle))
+            .map(|(_, transform)| transform.translation.xy())
+    };
+
+    let inputs = local_players
+        .0
+        .iter()
+        .map(|&handle| {
+            let own = position(&|other| other == handle);
+            let target = position(&|other| other != handle);
+            let aim = target.zip(own).map(|(target, own)| target - own);
+            (handle, scripted_input(handle, frame.0, aim))
+        })
+        .collect();
+    commands.insert_resource(LocalInputs::<Config>(inputs));
+}
+
+/// Stands in for the sprites the presentation adds, which move entities into
+/// another archetype and so change the order queries visit them in.
+#[derive(Component)]
+struct Decoration;
+
+/// Decorates the first player only, so this peer's queries visit players in
+/// another order than undecorated peers do.
+fn decorate_first_player(
+    mut commands: Commands,
+    players: Query<(Entity, &Player), Without<Decoration>>,
+) {
+    for (entity, player) in &players {
+        if player.handle == 0 {
+            commands.entity(entity).insert(Decoration);
+        }
+    }
+}
+
+/// A headless peer, playing against every other one on the network.
+fn headless_app(id: PeerId, players: &[PeerId], socket: LoopbackSocket) -> App {
+    let mut session_builder = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(players.len())
+        .with_input_delay(0)
+        .with_desync_detection_mode(DesyncDetection::On { interval: 10 })
+        .with_fps(FPS)
+        .expect("invalid fps");
+
+    for (handle, &player) in players.iter().enumerate() {
+        let player_type = if player == id {
+            ggrs::PlayerType::Local
+        } else {
+            ggrs::PlayerType::Remote(player)
+        };
+        session_builder = session_builder
+            .add_player(player_type, handle)
+            .expect("failed to add player");
+    }
+    let session = session_builder
+        .start_p2p_session(socket)
+        .expect("failed to start session");
+
+    let seed = MatchSeed::from_players(players);
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, GgrsPlugin::<Config>::default(), GamePlugin))
+        // Every update is one rollback frame, however long it really takes.
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1. / FPS as f64,
+        )))
+        .insert_resource(Args {
+            players: players.len(),
+            ..default()
+        })
+        .insert_resource(Arena::generate(seed))
+        .insert_resource(seed)
+        .insert_resource(Session::P2P(session))
+        .init_resource::<History>()
+        .add_systems(ReadInputs, read_scripted_inputs)
+        .add_systems(SaveWorld, take_snapshot);
+    app
+}
+
+fn confirmed_frame(app: &App) -> i32 {
+    match app.world().resource::<Session<Config>>() {
+        Session::P2P(session) => session.confirmed_frame(),
+        _ => unreachable!("headless apps only run p2p sessions"),
+    }
+}
+
+/// Updates every peer once, failing as soon as one of them desyncs.
+fn update(apps: &mut [App]) {
+    for app in apps {
+        app.update();
+
+        let Session::P2P(session) = &mut *app.world_mut().resource_mut::<Session<Config>>() else {
+            unreachable!();
+        };
+        for event in session.events() {
+            if let GgrsEvent::DesyncDetected { frame, addr, .. } = event {
+                panic!("desync with {addr} on frame {frame}");
+            }
+        }
+    }
+}
+
+#[test]
+fn peers_stay_in_sync() {
+    const FRAMES: i32 = 600;
+
+    let players = [1, 2].map(|id| PeerId(uuid::Uuid::from_u128(id)));
+    let mut apps: Vec<_> = LoopbackSocket::connect(&players)
+        .into_iter()
+        .zip(players)
+        .map(|(socket, id)| headless_app(id, &players, socket))
+        .collect();
+    apps[1].add_systems(Update, decorate_first_player);
+
+    // Synchronizing takes a few round trips before the first frame.
+    for _ in 0..FRAMES * 4 {
+        if apps.iter().all(|app| confirmed_frame(app) >= FRAMES) {
+            break;
+        }
+        update(&mut apps);
+    }
+    let confirmed = apps.iter().map(confirmed_frame).min().unwrap();
+    assert!(
+        confirmed >= FRAMES,
+        "only {confirmed} frames were confirmed"
+    );
+
+    // Peers only roll back to correct their predictions on their next frame.
+    for _ in 0..10 {
+        update(&mut apps);
+    }
+
+    let [a, b] = [&apps[0], &apps[1]].map(|app| {
+        let history = &app.world().resource::<History>().0;
+        history.range(..=confirmed).collect::<Vec<_>>()
+    });
+    assert_eq!(a.len(), b.len());
+    for ((frame, a), (_, b)) in a.iter().zip(&b) {
+        assert_eq!(a, b, "peers disagree on frame {frame}");
+    }
+
+    // The script isn't just standing around.
+    assert!(a.iter().any(|(_, snapshot)| !snapshot.bullets.is_empty()));
+    // Players got shot, and came back for the next round.
+    let ended = a
+        .iter()
+        .position(|(_, snapshot)| matches!(snapshot.round_state, RoundState::Ended { .. }))
+        .expect("nobody got shot");
+    assert!(
+        a[ended..].iter().any(|(_, snapshot)| {
+            snapshot.round_state == RoundState::InRound && snapshot.players.len() == 2
+        }),
+        "players didn't come back after the round ended"
+    );
+    for (frame, snapshot) in &a {
+        assert_eq!(
+            snapshot.rollback_entities,
+            snapshot.players.len() + snapshot.bullets.len(),
+            "entities without a marker on frame {frame}"
+        );
+    }
+}
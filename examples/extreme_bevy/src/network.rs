@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+use bevy_ggrs::*;
+use bevy_matchbox::prelude::*;
+
+use crate::{
+    arena::{self, Arena, MatchSeed},
+    args::{Args, Mode},
+    components::*,
+    connection::ConnectionPlugin,
+    game::{MatchWinner, Round, RoundState, Scores, FPS},
+    input::read_local_inputs,
+    lobby::{is_online, LobbyPlugin},
+    replay::{is_replaying, ReplayPlugin},
+    rules::CaptureProgress,
+    sound::SoundRequests,
+    Config, GameState,
+};
+
+const SYNCTEST_CHECK_DISTANCE: usize = 7;
+
+/// Everything that feeds the game: GGRS sessions, whether they're played
+/// through matchbox, locally or from a replay, and the local inputs.
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            GgrsPlugin::<Config>::default(),
+            LobbyPlugin,
+            ConnectionPlugin,
+            ReplayPlugin,
+        ))
+        .add_systems(
+            OnEnter(GameState::Matchmaking),
+            start_local_session.run_if(not(is_online).and(not(is_replaying))),
+        )
+        .add_systems(OnExit(GameState::InGame), leave_match)
+        .add_systems(ReadInputs, read_local_inputs.run_if(not(is_replaying)));
+    }
+}
+
+/// Throws away the session and everything that was rolled back with it, so
+/// the next match starts from scratch.
+fn leave_match(
+    mut commands: Commands,
+    players: Query<Entity, With<Player>>,
+    bullets: Query<Entity, With<Bullet>>,
+) {
+    commands.remove_resource::<Session<Config>>();
+    commands.remove_resource::<MatchboxSocket>();
+    commands.remove_resource::<Arena>();
+    commands.remove_resource::<MatchSeed>();
+    commands.insert_resource(RoundState::default());
+    commands.insert_resource(Round::default());
+    commands.insert_resource(Scores::default());
+    commands.insert_resource(MatchWinner::default());
+    commands.insert_resource(CaptureProgress::default());
+    commands.insert_resource(SoundRequests::default());
+
+    for entity in players.iter().chain(&bullets) {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Starts a session where every player is on this machine, so no matchbox
+/// server is needed.
+fn start_local_session(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    args: Res<Args>,
+) {
+    // A synctest session rolls back this many frames every frame and
+    // compares checksums after resimulating them. Zero disables the checks,
+    // which is all the local mode needs.
+    let check_distance = match args.mode {
+        Mode::SyncTest => SYNCTEST_CHECK_DISTANCE,
+        _ => 0,
+    };
+    info!("Starting a {:?} session", args.mode);
+
+    commands.insert_resource(synctest_session(
+        args.players,
+        args.input_delay,
+        check_distance,
+    ));
+    // Nobody else needs to agree on the seed, so every match gets a new map.
+    arena::start_match(&mut commands, MatchSeed(rand::random()));
+
+    next_state.set(GameState::InGame);
+}
+
+/// A session where every player is local.
+pub fn synctest_session(
+    players: usize,
+    input_delay: usize,
+    check_distance: usize,
+) -> Session<Config> {
+    let mut session_builder = ggrs::SessionBuilder::<Config>::new()
+        .with_num_players(players)
+        .with_input_delay(input_delay)
+        .with_check_distance(check_distance)
+        .with_fps(FPS)
+        .expect("invalid fps");
+
+    for i in 0..players {
+        session_builder = session_builder
+            .add_player(ggrs::PlayerType::Local, i)
+            .expect("failed to add player");
+    }
+
+    let ggrs_session = session_builder
+        .start_synctest_session()
+        .expect("failed to start session");
+
+    Session::SyncTest(ggrs_session)
+}
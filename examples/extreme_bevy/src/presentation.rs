@@ -0,0 +1,372 @@
+use bevy::{prelude::*, render::camera::ScalingMode};
+use bevy_ggrs::*;
+
+use crate::{
+    arena::ArenaPlugin,
+    args::{Args, MAX_PLAYERS},
+    components::*,
+    game::{MatchWinner, RoundState, Scores, MAP_SIZE},
+    input::PlayerInput,
+    rules::{CaptureProgress, Ruleset, CAPTURE_FRAMES, ZONE_HALF_SIZE},
+    sound::SoundPlugin,
+    spectator::{CameraTarget, SpectatorPlugin},
+    stats::NetworkStatsPlugin,
+    Config, GameState, ImageAssets,
+};
+
+const GRID_WIDTH: f32 = 0.05;
+const PLAYER_COLORS: [Color; MAX_PLAYERS] = [
+    Color::srgb(0., 0.47, 1.),
+    Color::srgb(0.9, 0.2, 0.2),
+    Color::srgb(0.2, 0.75, 0.3),
+    Color::srgb(0.95, 0.75, 0.1),
+];
+/// Where the nameplate of a local player sits, relative to them.
+const NAMEPLATE_OFFSET: Vec3 = Vec3::new(0., 0.9, 200.);
+
+#[derive(Component)]
+struct ScoreText;
+
+/// Makes the camera glide after the players it follows, instead of jumping
+/// along with every rollback correction.
+#[derive(Component)]
+struct FollowCamera {
+    /// How quickly the camera catches up, higher is snappier.
+    smoothing: f32,
+    /// How far ahead of a local player the camera looks when they aim, zero
+    /// keeps them centered.
+    aim_lead: f32,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        Self {
+            smoothing: 8.,
+            aim_lead: 2.,
+        }
+    }
+}
+
+/// Shows which player is controlled on this machine.
+#[derive(Component)]
+struct Nameplate {
+    handle: usize,
+}
+
+/// Everything that's drawn: the camera, the map, sprites for what the game
+/// spawns and the in-game UI. None of it is rolled back.
+pub struct PresentationPlugin;
+
+impl Plugin for PresentationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            ArenaPlugin,
+            SpectatorPlugin,
+            NetworkStatsPlugin,
+            SoundPlugin,
+        ))
+        .insert_resource(ClearColor(Color::srgb(0.53, 0.53, 0.53)))
+        .add_systems(OnExit(GameState::AssetLoading), setup)
+        .add_systems(
+            OnEnter(GameState::InGame),
+            (setup_score_text, setup_capture_zone),
+        )
+        .add_systems(
+            Update,
+            (
+                add_sprites,
+                camera_follow,
+                update_nameplates,
+                update_score_text,
+            )
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        Camera2d,
+        FollowCamera::default(),
+        OrthographicProjection {
+            scaling_mode: ScalingMode::FixedVertical {
+                viewport_height: 10.,
+            },
+            ..OrthographicProjection::default_2d()
+        },
+    ));
+
+    // Horizontal lines
+    for i in 0..=MAP_SIZE {
+        commands.spawn((
+            Transform::from_translation(Vec3::new(0., i as f32 - MAP_SIZE as f32 / 2., 0.)),
+            Sprite {
+                color: Color::srgb(0.27, 0.27, 0.27),
+                custom_size: Some(Vec2::new(MAP_SIZE as f32, GRID_WIDTH)),
+                ..default()
+            },
+        ));
+    }
+
+    // Vertical lines
+    for i in 0..=MAP_SIZE {
+        commands.spawn((
+            Transform::from_translation(Vec3::new(i as f32 - MAP_SIZE as f32 / 2., 0., 0.)),
+            Sprite {
+                color: Color::srgb(0.27, 0.27, 0.27),
+                custom_size: Some(Vec2::new(GRID_WIDTH, MAP_SIZE as f32)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// The game only spawns what it needs to simulate, sprites are added
+/// afterwards to whatever doesn't have one yet.
+fn add_sprites(
+    mut commands: Commands,
+    images: Res<ImageAssets>,
+    players: Query<(Entity, &Player), Without<Sprite>>,
+    bullets: Query<Entity, (With<Bullet>, Without<Sprite>)>,
+) {
+    for (entity, player) in &players {
+        commands.entity(entity).insert(Sprite {
+            color: PLAYER_COLORS[player.handle % MAX_PLAYERS],
+            custom_size: Some(Vec2::ONE),
+            ..default()
+        });
+    }
+    for entity in &bullets {
+        commands.entity(entity).insert(Sprite {
+            image: images.bullet.clone(),
+            custom_size: Some(Vec2::new(0.3, 0.1)),
+            ..default()
+        });
+    }
+}
+
+/// Eases the camera toward the players it follows. It runs after the rollback
+/// schedule, so it sees corrected positions but never affects the game.
+fn camera_follow(
+    local_players: Res<LocalPlayers>,
+    local_inputs: Option<Res<LocalInputs<Config>>>,
+    target: Option<Res<CameraTarget>>,
+    players: Query<(&Player, &Transform)>,
+    mut cameras: Query<(&FollowCamera, &OrthographicProjection, &mut Transform), Without<Player>>,
+    time: Res<Time>,
+) {
+    let followed: Vec<_> = players
+        .iter()
+        .filter(|(player, _)| match target.as_deref() {
+            Some(CameraTarget::Player(target)) => player.handle == *target,
+            Some(CameraTarget::Free) => false,
+            None => local_players.0.contains(&player.handle),
+        })
+        .collect();
+    if followed.is_empty() {
+        return;
+    }
+
+    // Players sharing a screen are all kept in view, so only a lone player
+    // gets to look ahead.
+    let center = followed
+        .iter()
+        .map(|(_, transform)| transform.translation.xy())
+        .sum::<Vec2>()
+        / followed.len() as f32;
+    let aim = match followed[..] {
+        [(player, _)] => local_inputs
+            .as_ref()
+            .and_then(|inputs| inputs.0.get(&player.handle))
+            .and_then(PlayerInput::aim),
+        _ => None,
+    };
+
+    for (follow, projection, mut transform) in &mut cameras {
+        let target = center + aim.unwrap_or_default() * follow.aim_lead;
+        let pos = smooth_follow(
+            transform.translation.xy(),
+            target,
+            projection.area.half_size(),
+            follow.smoothing * time.delta_secs(),
+        );
+        transform.translation.x = pos.x;
+        transform.translation.y = pos.y;
+    }
+}
+
+/// Moves `pos` part of the way to `target`, the same distance whatever the
+/// frame rate, without letting a view of `half_view` leave the arena.
+fn smooth_follow(pos: Vec2, target: Vec2, half_view: Vec2, smoothing: f32) -> Vec2 {
+    // When the arena is smaller than the view, it's kept centered instead.
+    let limit = (Vec2::splat(MAP_SIZE as f32 / 2.) - half_view).max(Vec2::ZERO);
+    let target = target.clamp(-limit, limit);
+
+    pos.lerp(target, 1. - (-smoothing).exp())
+}
+
+fn update_nameplates(
+    mut commands: Commands,
+    local_players: Option<Res<LocalPlayers>>,
+    players: Query<(&Player, &Transform)>,
+    mut nameplates: Query<(&Nameplate, &mut Transform, &mut Visibility), Without<Player>>,
+) {
+    // The session only reports its local players once it's running.
+    let Some(local_players) = local_players else {
+        return;
+    };
+
+    for &handle in &local_players.0 {
+        if nameplates
+            .iter()
+            .any(|(nameplate, ..)| nameplate.handle == handle)
+        {
+            continue;
+        }
+        // With several players on one machine, "you" doesn't say which one.
+        let label = match local_players.0.len() {
+            1 => "You".to_string(),
+            _ => format!("P{}", handle + 1),
+        };
+        commands.spawn((
+            Nameplate { handle },
+            StateScoped(GameState::InGame),
+            Text2d::new(label),
+            TextFont {
+                font_size: 30.,
+                ..default()
+            },
+            TextColor(PLAYER_COLORS[handle % MAX_PLAYERS]),
+            Transform::from_scale(Vec3::splat(1. / 60.)),
+            Visibility::Hidden,
+        ));
+    }
+
+    for (nameplate, mut transform, mut visibility) in &mut nameplates {
+        let player = players
+            .iter()
+            .find(|(player, _)| player.handle == nameplate.handle);
+
+        *visibility = match player {
+            Some((_, player_transform)) => {
+                transform.translation = player_transform.translation.with_z(0.) + NAMEPLATE_OFFSET;
+                Visibility::Inherited
+            }
+            None => Visibility::Hidden,
+        };
+    }
+}
+
+fn setup_capture_zone(mut commands: Commands, args: Res<Args>) {
+    if args.ruleset != Ruleset::Capture {
+        return;
+    }
+
+    commands.spawn((
+        StateScoped(GameState::InGame),
+        Transform::from_xyz(0., 0., 1.),
+        Sprite {
+            color: Color::srgba(1., 1., 1., 0.25),
+            custom_size: Some(Vec2::splat(ZONE_HALF_SIZE * 2.)),
+            ..default()
+        },
+    ));
+}
+
+fn setup_score_text(mut commands: Commands) {
+    commands.spawn((
+        ScoreText,
+        StateScoped(GameState::InGame),
+        Text::new(""),
+        TextFont {
+            font_size: 40.,
+            ..default()
+        },
+        Node {
+            align_self: AlignSelf::Start,
+            justify_self: JustifySelf::Center,
+            margin: UiRect::top(Val::Px(10.)),
+            ..default()
+        },
+    ));
+}
+
+fn update_score_text(
+    args: Res<Args>,
+    round_state: Res<RoundState>,
+    scores: Res<Scores>,
+    winner: Res<MatchWinner>,
+    capture: Res<CaptureProgress>,
+    mut texts: Query<&mut Text, With<ScoreText>>,
+) {
+    let scores = scores
+        .0
+        .iter()
+        .map(|(handle, score)| match capture.0.get(handle) {
+            // How much of the zone they captured this round.
+            Some(held) if args.ruleset == Ruleset::Capture => format!(
+                "P{}: {score} ({}%)",
+                handle + 1,
+                held * 100 / CAPTURE_FRAMES
+            ),
+            _ => format!("P{}: {score}", handle + 1),
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+
+    for mut text in &mut texts {
+        text.0 = match (*round_state, winner.0) {
+            (RoundState::Ended { .. }, Some(handle)) => {
+                format!("P{} wins the match!  {scores}", handle + 1)
+            }
+            (RoundState::Ended { .. }, None) => format!("Round over!  {scores}"),
+            _ => scores.clone(),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camera_catches_up_smoothly() {
+        let half_view = Vec2::new(8., 5.);
+        let target = Vec2::new(3., -2.);
+
+        let mut pos = Vec2::ZERO;
+        let mut last_distance = pos.distance(target);
+        for _ in 0..60 {
+            pos = smooth_follow(pos, target, half_view, 8. / 60.);
+            let distance = pos.distance(target);
+            assert!(distance < last_distance);
+            last_distance = distance;
+        }
+        assert!(last_distance < 0.01);
+
+        // Two half frames get as far as a whole one.
+        let half = smooth_follow(Vec2::ZERO, target, half_view, 4. / 60.);
+        let whole = smooth_follow(Vec2::ZERO, target, half_view, 8. / 60.);
+        assert!(smooth_follow(half, target, half_view, 4. / 60.).distance(whole) < 1e-5);
+    }
+
+    #[test]
+    fn camera_stays_inside_the_arena() {
+        let half_view = Vec2::new(8., 5.);
+        let corner = Vec2::splat(MAP_SIZE as f32);
+
+        let mut pos = Vec2::ZERO;
+        for _ in 0..1000 {
+            pos = smooth_follow(pos, corner, half_view, 1.);
+        }
+        let limit = MAP_SIZE as f32 / 2.;
+        assert!((pos.x + half_view.x - limit).abs() < 1e-3);
+        assert!((pos.y + half_view.y - limit).abs() < 1e-3);
+
+        // A view wider than the arena stays in the middle.
+        assert_eq!(
+            smooth_follow(Vec2::ZERO, corner, Vec2::splat(limit + 1.), 1.),
+            Vec2::ZERO
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_ggrs::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::{self, MatchSeed},
+    args::{Args, Mode, MAX_PLAYERS, MIN_PLAYERS},
+    input::PlayerInput,
+    network::synctest_session,
+    rules::Ruleset,
+    Config, GameState,
+};
+
+/// Where finished matches are saved, relative to the working directory.
+#[cfg(not(target_arch = "wasm32"))]
+const REPLAY_DIR: &str = "replays";
+
+/// Everything needed to play a match again. The same players on the same map
+/// fed the same inputs end up in exactly the same state.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub players: usize,
+    pub ruleset: Ruleset,
+    /// The input delay of the match. The recorded inputs already include it,
+    /// it's only kept to help make sense of desyncs.
+    pub input_delay: usize,
+    /// Every player's input, by handle, for each rollback frame.
+    pub frames: Vec<Vec<PlayerInput>>,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes =
+            fs::read(path).map_err(|err| format!("can't read {}: {err}", path.display()))?;
+        let replay: Self = bincode::deserialize(&bytes)
+            .map_err(|err| format!("{} is not a replay: {err}", path.display()))?;
+
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&replay.players)
+            || replay
+                .frames
+                .iter()
+                .any(|inputs| inputs.len() != replay.players)
+        {
+            return Err(format!("{} is not a valid replay", path.display()));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let bytes = bincode::serialize(self).map_err(|err| err.to_string())?;
+        fs::write(path, bytes).map_err(|err| format!("can't write {}: {err}", path.display()))
+    }
+}
+
+/// The replay of the match being played.
+#[derive(Resource)]
+struct Recording {
+    seed: MatchSeed,
+    players: usize,
+    ruleset: Ruleset,
+    input_delay: usize,
+    /// Inputs by frame. Frames are recorded again whenever they're
+    /// resimulated, so predictions that were wrong get replaced by what the
+    /// players actually did.
+    frames: BTreeMap<i32, Vec<PlayerInput>>,
+    /// The last frame every player's input is known for. Frames up to it
+    /// won't be resimulated anymore, whether their prediction was right or
+    /// not.
+    confirmed_frame: i32,
+}
+
+impl Recording {
+    /// The replay up to the last confirmed frame.
+    fn finish(self) -> Replay {
+        Replay {
+            seed: self.seed.0,
+            players: self.players,
+            ruleset: self.ruleset,
+            input_delay: self.input_delay,
+            frames: self
+                .frames
+                .into_iter()
+                .take_while(|(frame, _)| *frame <= self.confirmed_frame)
+                .map(|(_, inputs)| inputs)
+                .collect(),
+        }
+    }
+}
+
+/// A replay being played back.
+#[derive(Resource)]
+struct Playback {
+    replay: Replay,
+    next_frame: usize,
+}
+
+/// Records every match to a replay file, and plays them back in replay mode.
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::Matchmaking),
+            start_replay_session.run_if(is_replaying),
+        )
+        .add_systems(
+            OnEnter(GameState::InGame),
+            start_recording.run_if(not(is_replaying)),
+        )
+        .add_systems(OnExit(GameState::InGame), (save_recording, stop_playback))
+        // Closing the window quits without leaving the match first.
+        .add_systems(Last, save_recording.run_if(on_event::<AppExit>))
+        .add_systems(ReadInputs, play_back_inputs.run_if(is_replaying))
+        .add_systems(GgrsSchedule, record_inputs);
+    }
+}
+
+pub fn is_replaying(args: Res<Args>) -> bool {
+    args.mode == Mode::Replay
+}
+
+fn start_replay_session(
+    mut commands: Commands,
+    mut args: ResMut<Args>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let path = args.replay.clone().expect("replay mode needs a replay");
+    let replay = match Replay::load(&path) {
+        Ok(replay) => replay,
+        Err(err) => {
+            error!("Failed to load replay: {err}");
+            next_state.set(GameState::MainMenu);
+            return;
+        }
+    };
+    info!(
+        "Replaying {path}: {} players, {}, {} frames, recorded with an input delay of {}",
+        replay.players,
+        replay.ruleset,
+        replay.frames.len(),
+        replay.input_delay
+    );
+
+    // The recorded inputs are the ones each frame was simulated with, they
+    // must not be delayed again.
+    commands.insert_resource(synctest_session(replay.players, 0, 0));
+    arena::start_match(&mut commands, MatchSeed(replay.seed));
+    args.players = replay.players;
+    args.ruleset = replay.ruleset;
+    commands.insert_resource(Playback {
+        replay,
+        next_frame: 0,
+    });
+
+    next_state.set(GameState::InGame);
+}
+
+fn play_back_inputs(
+    mut commands: Commands,
+    mut playback: ResMut<Playback>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let players = playback.replay.players;
+    let inputs = match playback.replay.frames.get(playback.next_frame) {
+        Some(inputs) => inputs.clone(),
+        None => {
+            if playback.next_frame == playback.replay.frames.len() {
+                info!("Replay over");
+                next_state.set(GameState::MainMenu);
+            }
+            vec![PlayerInput::default(); players]
+        }
+    };
+    playback.next_frame += 1;
+
+    commands.insert_resource(LocalInputs::<Config>(
+        inputs.into_iter().enumerate().collect(),
+    ));
+}
+
+fn stop_playback(mut commands: Commands) {
+    commands.remove_resource::<Playback>();
+}
+
+fn start_recording(mut commands: Commands, seed: Res<MatchSeed>, args: Res<Args>) {
+    commands.insert_resource(Recording {
+        seed: *seed,
+        players: args.players,
+        ruleset: args.ruleset,
+        input_delay: args.input_delay,
+        frames: BTreeMap::new(),
+        confirmed_frame: ggrs::NULL_FRAME,
+    });
+}
+
+fn record_inputs(
+    recording: Option<ResMut<Recording>>,
+    session: Option<Res<Session<Config>>>,
+    inputs: Res<PlayerInputs<Config>>,
+    frame: Res<RollbackFrameCount>,
+) {
+    let Some(mut recording) = recording else {
+        return;
+    };
+
+    // Only P2P sessions predict, every other one simulates known inputs.
+    let confirmed_frame = match session.as_deref() {
+        Some(Session::P2P(session)) => session.confirmed_frame(),
+        _ => frame.0,
+    };
+    recording.confirmed_frame = recording.confirmed_frame.max(confirmed_frame);
+    let inputs = inputs.iter().map(|(input, _)| *input).collect();
+    recording.frames.insert(frame.0, inputs);
+}
+
+fn save_recording(mut commands: Commands, recording: Option<ResMut<Recording>>) {
+    let Some(mut recording) = recording else {
+        return;
+    };
+    commands.remove_resource::<Recording>();
+
+    let recording = Recording {
+        frames: std::mem::take(&mut recording.frames),
+        ..*recording
+    };
+    save_replay(recording.finish());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_replay(replay: Replay) {
+    if replay.frames.is_empty() {
+        return;
+    }
+
+    let finished = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = Path::new(REPLAY_DIR).join(format!("match-{finished}.replay"));
+
+    let saved = fs::create_dir_all(REPLAY_DIR)
+        .map_err(|err| format!("can't create {REPLAY_DIR}: {err}"))
+        .and_then(|_| replay.save(&path));
+    match saved {
+        Ok(()) => info!(
+            "Saved a replay of {} frames to {}",
+            replay.frames.len(),
+            path.display()
+        ),
+        Err(err) => error!("Failed to save replay: {err}"),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_replay(_replay: Replay) {
+    info!("Replays are only saved on native builds");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(x: f32) -> PlayerInput {
+        PlayerInput::new(Vec2::new(x, 0.), Vec2::ZERO, x > 0.)
+    }
+
+    #[test]
+    fn predicted_frames_are_replaced_or_dropped() {
+        let mut recording = Recording {
+            seed: MatchSeed(3),
+            players: 2,
+            ruleset: Ruleset::Rounds,
+            input_delay: 2,
+            frames: BTreeMap::new(),
+            confirmed_frame: ggrs::NULL_FRAME,
+        };
+
+        // Predicted right, so never resimulated.
+        recording.frames.insert(0, vec![input(0.), input(0.)]);
+        // Predicted wrong, then resimulated once the remote input arrived.
+        recording.frames.insert(1, vec![input(1.), input(0.)]);
+        recording.frames.insert(1, vec![input(1.), input(-1.)]);
+        // Never confirmed before the match ended.
+        recording.frames.insert(2, vec![input(1.), input(-1.)]);
+        recording.confirmed_frame = 1;
+
+        let replay = recording.finish();
+        assert_eq!(replay.seed, 3);
+        assert_eq!(
+            replay.frames,
+            [vec![input(0.), input(0.)], vec![input(1.), input(-1.)]]
+        );
+    }
+
+    #[test]
+    fn replays_round_trip_through_files() {
+        let replay = Replay {
+            seed: u64::MAX,
+            players: 3,
+            ruleset: Ruleset::Kills { target: 7 },
+            input_delay: 1,
+            frames: vec![vec![input(0.5), input(-0.5), input(1.)]; 100],
+        };
+        let path = std::env::temp_dir().join("extreme_bevy_round_trip.replay");
+
+        replay.save(&path).unwrap();
+        assert_eq!(Replay::load(&path).unwrap(), replay);
+
+        fs::write(&path, b"not a replay").unwrap();
+        assert!(Replay::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
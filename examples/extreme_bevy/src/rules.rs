@@ -0,0 +1,185 @@
+mod capture;
+mod free_for_all;
+mod kills;
+mod rounds;
+
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::args::Args;
+
+pub use capture::{CaptureProgress, CAPTURE_FRAMES, ZONE_HALF_SIZE};
+#[cfg(test)]
+pub use rounds::score_survivors;
+
+/// Kills needed to win a match of [`Ruleset::Kills`], unless `--kills` says
+/// otherwise.
+pub const DEFAULT_KILL_TARGET: u32 = 10;
+
+/// How a match is won. Every peer has to play by the same rules, so it's
+/// part of what players agree on in the lobby, and of every replay.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Ruleset {
+    /// The first hit ends the round, everyone still standing scores.
+    #[default]
+    Rounds,
+    /// The round goes on until one player is left, who scores.
+    FreeForAll,
+    /// Every kill scores, whoever gets `target` of them wins the match.
+    Kills { target: u32 },
+    /// Holding the zone in the middle of the arena alone long enough wins
+    /// the round.
+    Capture,
+}
+
+/// Which [`Ruleset`] is played, without its settings. It picks the systems
+/// that run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulesetKind {
+    Rounds,
+    FreeForAll,
+    Kills,
+    Capture,
+}
+
+impl Ruleset {
+    /// The ruleset after this one in the menu, which plays for `kill_target`
+    /// kills when it's [`Ruleset::Kills`].
+    pub fn next(self, kill_target: u32) -> Self {
+        match self {
+            Ruleset::Rounds => Ruleset::FreeForAll,
+            Ruleset::FreeForAll => Ruleset::Kills {
+                target: kill_target,
+            },
+            Ruleset::Kills { .. } => Ruleset::Capture,
+            Ruleset::Capture => Ruleset::Rounds,
+        }
+    }
+
+    pub fn kind(&self) -> RulesetKind {
+        match self {
+            Ruleset::Rounds => RulesetKind::Rounds,
+            Ruleset::FreeForAll => RulesetKind::FreeForAll,
+            Ruleset::Kills { .. } => RulesetKind::Kills,
+            Ruleset::Capture => RulesetKind::Capture,
+        }
+    }
+
+    /// Parses the name used on the command line.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rounds" => Some(Ruleset::Rounds),
+            "ffa" => Some(Ruleset::FreeForAll),
+            "kills" => Some(Ruleset::Kills {
+                target: DEFAULT_KILL_TARGET,
+            }),
+            "capture" => Some(Ruleset::Capture),
+            _ => None,
+        }
+    }
+
+    /// Short name that tells apart every ruleset peers could disagree on.
+    pub fn id(&self) -> String {
+        match self {
+            Ruleset::Rounds => "rounds".to_string(),
+            Ruleset::FreeForAll => "ffa".to_string(),
+            Ruleset::Kills { target } => format!("kills-{target}"),
+            Ruleset::Capture => "capture".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ruleset::Rounds => write!(f, "Rounds"),
+            Ruleset::FreeForAll => write!(f, "Free-for-all"),
+            Ruleset::Kills { target } => write!(f, "First to {target} kills"),
+            Ruleset::Capture => write!(f, "Capture the zone"),
+        }
+    }
+}
+
+/// Runs the rules of the match being played. Each ruleset is a plugin of its
+/// own, adding systems to [`RulesSet`](crate::game::RulesSet) that only run
+/// when it's picked.
+pub struct RulesPlugin;
+
+impl Plugin for RulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            rounds::RoundsPlugin,
+            free_for_all::FreeForAllPlugin,
+            kills::KillsPlugin,
+            capture::CapturePlugin,
+        ));
+    }
+}
+
+/// Whether the match is played by a ruleset of this kind, whatever its
+/// settings.
+fn playing(kind: RulesetKind) -> impl Fn(Res<Args>) -> bool {
+    move |args: Res<Args>| args.ruleset.kind() == kind
+}
+
+#[cfg(test)]
+mod test_app {
+    use bevy_ggrs::GgrsSchedule;
+
+    use super::*;
+    use crate::{
+        components::Player,
+        game::{self, Hit, Hits, MatchWinner, RoundState, Scores},
+    };
+
+    /// An app in the middle of a round between `players` players standing in
+    /// the middle of the arena, running every ruleset in [`RulesSet`] the way
+    /// [`GamePlugin`](crate::game::GamePlugin) sets it up.
+    ///
+    /// [`RulesSet`]: crate::game::RulesSet
+    pub fn rules_app(ruleset: Ruleset, players: usize) -> App {
+        let mut app = App::new();
+        app.insert_resource(Args {
+            players,
+            ruleset,
+            ..default()
+        })
+        .insert_resource(RoundState::InRound)
+        .init_resource::<Scores>()
+        .init_resource::<MatchWinner>()
+        .init_resource::<Hits>()
+        .add_plugins(RulesPlugin)
+        .configure_sets(GgrsSchedule, game::rules_set());
+        for handle in 0..players {
+            app.world_mut()
+                .spawn((Player { handle }, Transform::from_xyz(0., 0., 100.)));
+        }
+        app
+    }
+
+    /// Simulates a frame on which `hits` were scored, as `(victim, shooter)`.
+    /// Like in the game, the victims are gone by the time the rules run.
+    pub fn run_frame(app: &mut App, hits: &[(usize, usize)]) {
+        for &(victim, _) in hits {
+            if let Some(entity) = find_player(app, victim) {
+                app.world_mut().despawn(entity);
+            }
+        }
+        app.world_mut().resource_mut::<Hits>().0 = hits
+            .iter()
+            .map(|&(victim, shooter)| Hit { victim, shooter })
+            .collect();
+        app.world_mut().run_schedule(GgrsSchedule);
+    }
+
+    pub fn find_player(app: &mut App, handle: usize) -> Option<Entity> {
+        let world = app.world_mut();
+        world
+            .query::<(Entity, &Player)>()
+            .iter(world)
+            .find(|(_, player)| player.handle == handle)
+            .map(|(entity, _)| entity)
+    }
+}
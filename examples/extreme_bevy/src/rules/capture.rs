@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_ggrs::*;
+
+use crate::{
+    components::Player,
+    game::{Hits, RoundState, RulesSet, Scores, FPS},
+    rules::{playing, RulesetKind},
+};
+
+/// Half the size of the zone in the middle of the arena. It fits inside the
+/// area kept clear of walls.
+pub const ZONE_HALF_SIZE: f32 = 2.;
+/// How long a player has to hold the zone to win the round, in rollback
+/// frames. It doesn't need to be in one go.
+pub const CAPTURE_FRAMES: u32 = 5 * FPS as u32;
+
+/// Rollback frames each player held the zone for this round, by handle.
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CaptureProgress(pub BTreeMap<usize, u32>);
+
+/// Players score a round by standing in the zone alone long enough. Getting
+/// shot only takes you out until the next round, and the round is a draw
+/// once everyone is.
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.rollback_resource_with_clone::<CaptureProgress>()
+            .checksum_resource_with_hash::<CaptureProgress>()
+            .init_resource::<CaptureProgress>()
+            .add_systems(
+                GgrsSchedule,
+                capture_zone
+                    .in_set(RulesSet)
+                    .run_if(playing(RulesetKind::Capture)),
+            );
+    }
+}
+
+fn in_zone(pos: Vec2) -> bool {
+    pos.abs().cmple(Vec2::splat(ZONE_HALF_SIZE)).all()
+}
+
+fn capture_zone(
+    hits: Res<Hits>,
+    mut round_state: ResMut<RoundState>,
+    mut scores: ResMut<Scores>,
+    mut progress: ResMut<CaptureProgress>,
+    players: Query<(&Player, &Transform)>,
+) {
+    let alive: Vec<_> = players
+        .iter()
+        .filter(|(player, _)| !hits.0.iter().any(|hit| hit.victim == player.handle))
+        .collect();
+
+    if alive.is_empty() {
+        info!("Everyone is out, nobody captured the zone");
+        progress.0.clear();
+        round_state.end();
+        return;
+    }
+
+    // A contested zone doesn't count for anyone.
+    let mut holders = alive
+        .iter()
+        .filter(|(_, transform)| in_zone(transform.translation.xy()));
+    let (Some((holder, _)), None) = (holders.next(), holders.next()) else {
+        return;
+    };
+
+    let held = progress.0.entry(holder.handle).or_default();
+    *held += 1;
+    if *held >= CAPTURE_FRAMES {
+        *scores.0.entry(holder.handle).or_default() += 1;
+        info!(
+            "P{} captured the zone, scores: {:?}",
+            holder.handle + 1,
+            scores.0
+        );
+        progress.0.clear();
+        round_state.end();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{test_app::*, Ruleset};
+
+    #[test]
+    fn holding_the_zone_alone_wins_the_round() {
+        let mut app = rules_app(Ruleset::Capture, 2);
+
+        // Contested, nobody makes progress.
+        for _ in 0..CAPTURE_FRAMES {
+            run_frame(&mut app, &[]);
+        }
+        assert!(app.world().resource::<CaptureProgress>().0.is_empty());
+
+        let contender = find_player(&mut app, 1).unwrap();
+        app.world_mut()
+            .entity_mut(contender)
+            .insert(Transform::from_xyz(8., 0., 100.));
+        for _ in 0..CAPTURE_FRAMES - 1 {
+            run_frame(&mut app, &[]);
+        }
+        assert_eq!(*app.world().resource::<RoundState>(), RoundState::InRound);
+        assert!(app.world().resource::<Scores>().0.is_empty());
+
+        run_frame(&mut app, &[]);
+        assert!(matches!(
+            *app.world().resource::<RoundState>(),
+            RoundState::Ended { .. }
+        ));
+        assert_eq!(app.world().resource::<Scores>().0, BTreeMap::from([(0, 1)]));
+        assert!(app.world().resource::<CaptureProgress>().0.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+use bevy_ggrs::GgrsSchedule;
+
+use crate::{
+    components::Player,
+    game::{Hits, RoundState, RulesSet, Scores},
+    rules::{playing, RulesetKind},
+};
+
+/// Everyone fights until a single player is left, who wins the round.
+pub struct FreeForAllPlugin;
+
+impl Plugin for FreeForAllPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GgrsSchedule,
+            last_one_standing
+                .in_set(RulesSet)
+                .run_if(playing(RulesetKind::FreeForAll)),
+        );
+    }
+}
+
+fn last_one_standing(
+    hits: Res<Hits>,
+    mut round_state: ResMut<RoundState>,
+    mut scores: ResMut<Scores>,
+    players: Query<&Player>,
+) {
+    if hits.0.is_empty() {
+        return;
+    }
+
+    let mut survivors = players
+        .iter()
+        .filter(|player| !hits.0.iter().any(|hit| hit.victim == player.handle));
+    match (survivors.next(), survivors.next()) {
+        (Some(_), Some(_)) => return,
+        (Some(winner), None) => *scores.0.entry(winner.handle).or_default() += 1,
+        // Shot each other on the same frame, nobody wins.
+        (None, _) => {}
+    }
+    info!("Round over, scores: {:?}", scores.0);
+    round_state.end();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::rules::{test_app::*, Ruleset};
+
+    #[test]
+    fn round_goes_on_until_one_is_left() {
+        let mut app = rules_app(Ruleset::FreeForAll, 3);
+
+        // Player 3 went down, two are still fighting.
+        run_frame(&mut app, &[(2, 0)]);
+        assert_eq!(*app.world().resource::<RoundState>(), RoundState::InRound);
+        assert!(app.world().resource::<Scores>().0.is_empty());
+
+        // Player 1 gets player 2, and the round.
+        run_frame(&mut app, &[(1, 0)]);
+        assert!(matches!(
+            *app.world().resource::<RoundState>(),
+            RoundState::Ended { .. }
+        ));
+        assert_eq!(app.world().resource::<Scores>().0, BTreeMap::from([(0, 1)]));
+    }
+}
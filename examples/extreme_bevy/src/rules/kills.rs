@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_ggrs::GgrsSchedule;
+
+use crate::{
+    args::Args,
+    components::Player,
+    game::{Hits, MatchWinner, RoundState, RulesSet, Scores},
+    rules::{playing, Ruleset, RulesetKind},
+};
+
+/// Every kill is a point, and the first player to reach the target wins the
+/// match. Rounds go on until one player is left, so kills keep coming.
+pub struct KillsPlugin;
+
+impl Plugin for KillsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GgrsSchedule,
+            count_kills
+                .in_set(RulesSet)
+                .run_if(playing(RulesetKind::Kills)),
+        );
+    }
+}
+
+fn count_kills(
+    args: Res<Args>,
+    hits: Res<Hits>,
+    mut round_state: ResMut<RoundState>,
+    mut scores: ResMut<Scores>,
+    mut winner: ResMut<MatchWinner>,
+    players: Query<&Player>,
+) {
+    let Ruleset::Kills { target } = args.ruleset else {
+        return;
+    };
+    if hits.0.is_empty() {
+        return;
+    }
+
+    for hit in &hits.0 {
+        // Walking into your own bullet doesn't count.
+        if hit.shooter != hit.victim {
+            *scores.0.entry(hit.shooter).or_default() += 1;
+        }
+    }
+
+    // Reaching the target on the same frame, the most kills win, then the
+    // lowest handle.
+    let leader = scores
+        .0
+        .iter()
+        .max_by_key(|(handle, kills)| (**kills, std::cmp::Reverse(**handle)));
+    if let Some((&handle, _)) = leader.filter(|(_, &kills)| kills >= target) {
+        info!("P{} wins the match, scores: {:?}", handle + 1, scores.0);
+        winner.0 = Some(handle);
+        round_state.end();
+        return;
+    }
+
+    let survivors = players
+        .iter()
+        .filter(|player| !hits.0.iter().any(|hit| hit.victim == player.handle))
+        .count();
+    if survivors <= 1 {
+        info!("Round over, scores: {:?}", scores.0);
+        round_state.end();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::rules::test_app::*;
+
+    #[test]
+    fn kills_score_for_the_shooter() {
+        let mut app = rules_app(Ruleset::Kills { target: 10 }, 3);
+
+        // Player 2 walks into their own bullet.
+        run_frame(&mut app, &[(0, 2), (1, 1)]);
+
+        assert_eq!(app.world().resource::<Scores>().0, BTreeMap::from([(2, 1)]));
+        assert_eq!(app.world().resource::<MatchWinner>().0, None);
+        // Player 3 is the only one left.
+        assert!(matches!(
+            *app.world().resource::<RoundState>(),
+            RoundState::Ended { .. }
+        ));
+    }
+
+    #[test]
+    fn first_to_the_target_wins() {
+        let mut app = rules_app(Ruleset::Kills { target: 2 }, 3);
+        app.world_mut().resource_mut::<Scores>().0 = BTreeMap::from([(0, 1), (1, 1)]);
+
+        run_frame(&mut app, &[(2, 1)]);
+
+        assert_eq!(app.world().resource::<MatchWinner>().0, Some(1));
+        assert!(matches!(
+            *app.world().resource::<RoundState>(),
+            RoundState::Ended { .. }
+        ));
+    }
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy_ggrs::GgrsSchedule;
+
+use crate::{
+    components::Player,
+    game::{Hits, RoundState, RulesSet, Scores},
+    rules::{playing, RulesetKind},
+};
+
+/// The first hit ends the round, and everyone still standing scores.
+pub struct RoundsPlugin;
+
+impl Plugin for RoundsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            GgrsSchedule,
+            score_survivors
+                .in_set(RulesSet)
+                .run_if(playing(RulesetKind::Rounds)),
+        );
+    }
+}
+
+pub fn score_survivors(
+    hits: Res<Hits>,
+    mut round_state: ResMut<RoundState>,
+    mut scores: ResMut<Scores>,
+    players: Query<&Player>,
+) {
+    if hits.0.is_empty() {
+        return;
+    }
+
+    for player in &players {
+        if !hits.0.iter().any(|hit| hit.victim == player.handle) {
+            *scores.0.entry(player.handle).or_default() += 1;
+        }
+    }
+    info!("Round over, scores: {:?}", scores.0);
+    round_state.end();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{
+        game::MatchWinner,
+        rules::{test_app::*, Ruleset},
+    };
+
+    #[test]
+    fn survivors_score_when_the_round_ends() {
+        let mut app = rules_app(Ruleset::Rounds, 3);
+
+        run_frame(&mut app, &[]);
+        assert_eq!(*app.world().resource::<RoundState>(), RoundState::InRound);
+
+        run_frame(&mut app, &[(0, 1)]);
+        assert!(matches!(
+            *app.world().resource::<RoundState>(),
+            RoundState::Ended { .. }
+        ));
+        // Only these rules ran, the shooter doesn't score for the kill.
+        assert_eq!(
+            app.world().resource::<Scores>().0,
+            BTreeMap::from([(1, 1), (2, 1)])
+        );
+        assert_eq!(app.world().resource::<MatchWinner>().0, None);
+
+        // Nothing scores until the next round.
+        run_frame(&mut app, &[(1, 2)]);
+        assert_eq!(
+            app.world().resource::<Scores>().0,
+            BTreeMap::from([(1, 1), (2, 1)])
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+
+/// Where native builds read their window settings from, relative to the
+/// working directory.
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_FILE: &str = "settings.ron";
+
+/// How the game's window is set up on native builds, read from
+/// `settings.ron`. Missing fields keep their default, e.g.
+///
+/// ```ron
+/// (width: 1920, height: 1080, fullscreen: true, vsync: false)
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowSettings {
+    pub width: u32,
+    pub height: u32,
+    /// Borderless on the current monitor, at its resolution.
+    pub fullscreen: bool,
+    pub vsync: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for WindowSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            fullscreen: false,
+            vsync: true,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WindowSettings {
+    /// The settings in `settings.ron`, or the defaults when there's no such
+    /// file.
+    pub fn load() -> Result<Self, String> {
+        let text = match std::fs::read_to_string(SETTINGS_FILE) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(format!("can't read {SETTINGS_FILE}: {err}")),
+        };
+        Self::parse(&text).map_err(|err| format!("invalid {SETTINGS_FILE}: {err}"))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let settings: Self = ron::from_str(text).map_err(|err| err.to_string())?;
+        if settings.width == 0 || settings.height == 0 {
+            return Err(format!(
+                "resolution must not be empty, got {}x{}",
+                settings.width, settings.height
+            ));
+        }
+        Ok(settings)
+    }
+
+    pub fn window(&self) -> Window {
+        use bevy::window::{MonitorSelection, PresentMode, WindowMode};
+
+        Window {
+            title: "Extreme Bevy".to_string(),
+            resolution: (self.width as f32, self.height as f32).into(),
+            mode: if self.fullscreen {
+                WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+            } else {
+                WindowMode::Windowed
+            },
+            present_mode: if self.vsync {
+                PresentMode::AutoVsync
+            } else {
+                PresentMode::AutoNoVsync
+            },
+            ..default()
+        }
+    }
+}
+
+/// The window to play in, sized by the settings file on native builds. A
+/// broken file doesn't keep the game from starting, the window falls back to
+/// the defaults and the error is returned to be reported once logging works.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn primary_window() -> (Window, Option<String>) {
+    match WindowSettings::load() {
+        Ok(settings) => (settings.window(), None),
+        Err(err) => (WindowSettings::default().window(), Some(err)),
+    }
+}
+
+/// The window to play in. On the web it's the canvas, sized by the page.
+#[cfg(target_arch = "wasm32")]
+pub fn primary_window() -> (Window, Option<String>) {
+    let window = Window {
+        // fill the entire browser window
+        fit_canvas_to_parent: true,
+        // don't hijack keyboard shortcuts like F5, F6, F12, Ctrl+R etc.
+        prevent_default_event_handling: false,
+        ..default()
+    };
+    (window, None)
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_settings_keep_their_default() {
+        let settings = WindowSettings::parse("(fullscreen: true)").unwrap();
+        assert_eq!(
+            settings,
+            WindowSettings {
+                fullscreen: true,
+                ..default()
+            }
+        );
+        assert_eq!(WindowSettings::parse("()").unwrap(), default());
+
+        for text in ["(width: 0)", "(vsync: 1)", "(resolution: 4k)", "nonsense"] {
+            assert!(WindowSettings::parse(text).is_err(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use bevy_ggrs::*;
+
+use crate::{Config, GameState};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundEffect {
+    Shot,
+    Hit,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct SoundAssets {
+    #[asset(path = "shot.wav")]
+    shot: Handle<AudioSource>,
+    #[asset(path = "hit.wav")]
+    hit: Handle<AudioSource>,
+}
+
+/// Sounds the game asked for, by rollback frame.
+///
+/// Frames can be simulated several times before every input is known, so
+/// sounds are only played once their frame is confirmed. Simulating a frame
+/// again replaces what it asked for, which cancels sounds of predictions that
+/// turned out wrong.
+#[derive(Resource, Default)]
+pub struct SoundRequests {
+    /// The frame being simulated.
+    frame: i32,
+    requested: BTreeMap<i32, Vec<SoundEffect>>,
+    /// The last frame sounds were played for.
+    played: Option<i32>,
+}
+
+impl SoundRequests {
+    pub fn play(&mut self, effect: SoundEffect) {
+        // Sync tests resimulate frames that were confirmed long ago.
+        if self.played.is_some_and(|played| self.frame <= played) {
+            return;
+        }
+        self.requested.entry(self.frame).or_default().push(effect);
+    }
+
+    fn start_frame(&mut self, frame: i32) {
+        self.frame = frame;
+        self.requested.remove(&frame);
+    }
+
+    /// Sounds of every frame up to `confirmed` that weren't played yet.
+    fn take_confirmed(&mut self, confirmed: i32) -> Vec<SoundEffect> {
+        let pending = self.requested.split_off(&(confirmed + 1));
+        let confirmed_sounds = std::mem::replace(&mut self.requested, pending);
+        self.played = self.played.max(Some(confirmed));
+
+        confirmed_sounds.into_values().flatten().collect()
+    }
+}
+
+/// Runs first in the rollback schedule, so systems after it ask for sounds
+/// on the frame being simulated.
+pub fn start_sound_frame(mut sounds: ResMut<SoundRequests>, frame: Res<RollbackFrameCount>) {
+    sounds.start_frame(frame.0);
+}
+
+/// Plays the sounds the game asked for, once they can't be rolled back
+/// anymore.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            play_confirmed_sounds.run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+fn play_confirmed_sounds(
+    mut commands: Commands,
+    session: Option<Res<Session<Config>>>,
+    frame: Res<RollbackFrameCount>,
+    mut sounds: ResMut<SoundRequests>,
+    assets: Res<SoundAssets>,
+) {
+    // Spectators and local sessions never predict anything.
+    let confirmed = match session.as_deref() {
+        Some(Session::P2P(session)) => session.confirmed_frame(),
+        Some(_) => frame.0,
+        None => return,
+    };
+
+    for effect in sounds.take_confirmed(confirmed) {
+        let source = match effect {
+            SoundEffect::Shot => assets.shot.clone(),
+            SoundEffect::Hit => assets.hit.clone(),
+        };
+        commands.spawn((AudioPlayer::new(source), PlaybackSettings::DESPAWN));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mispredicted_sounds_are_cancelled() {
+        let mut sounds = SoundRequests::default();
+
+        sounds.start_frame(0);
+        sounds.play(SoundEffect::Shot);
+        sounds.start_frame(1);
+        sounds.play(SoundEffect::Hit);
+        assert_eq!(sounds.take_confirmed(0), [SoundEffect::Shot]);
+
+        // Frame 1 didn't have a hit after all.
+        sounds.start_frame(1);
+        sounds.start_frame(2);
+        sounds.play(SoundEffect::Shot);
+        assert_eq!(sounds.take_confirmed(2), [SoundEffect::Shot]);
+        assert!(sounds.take_confirmed(2).is_empty());
+    }
+
+    #[test]
+    fn resimulated_frames_play_once() {
+        let mut sounds = SoundRequests::default();
+
+        for _ in 0..3 {
+            sounds.start_frame(5);
+            sounds.play(SoundEffect::Hit);
+        }
+        assert_eq!(sounds.take_confirmed(5), [SoundEffect::Hit]);
+
+        // Played already, resimulating doesn't bring it back.
+        sounds.start_frame(5);
+        sounds.play(SoundEffect::Hit);
+        assert!(sounds.take_confirmed(6).is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use crate::{
+    args::{Args, Mode},
+    GameState,
+};
+
+/// Speed of the free camera, in world units per second.
+const FREE_CAMERA_SPEED: f32 = 10.;
+
+const FOLLOW_KEYS: [KeyCode; 4] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+];
+const FREE_CAMERA_KEY: KeyCode = KeyCode::KeyF;
+
+/// What a spectator's camera looks at. Players always follow themselves, so
+/// this only exists while spectating or watching a replay.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraTarget {
+    Player(usize),
+    Free,
+}
+
+/// Lets spectators switch between following each player and moving the
+/// camera around freely.
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            insert_camera_target.run_if(is_spectating),
+        )
+        .add_systems(OnExit(GameState::InGame), remove_camera_target)
+        .add_systems(
+            Update,
+            (choose_camera_target, move_free_camera)
+                .chain()
+                .run_if(in_state(GameState::InGame).and(resource_exists::<CameraTarget>)),
+        );
+    }
+}
+
+fn is_spectating(args: Res<Args>) -> bool {
+    matches!(args.mode, Mode::Spectate | Mode::Replay)
+}
+
+fn insert_camera_target(mut commands: Commands, args: Res<Args>) {
+    commands.insert_resource(match args.follow {
+        Some(handle) => CameraTarget::Player(handle),
+        None => CameraTarget::Free,
+    });
+}
+
+fn remove_camera_target(mut commands: Commands) {
+    commands.remove_resource::<CameraTarget>();
+}
+
+fn choose_camera_target(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut target: ResMut<CameraTarget>,
+    args: Res<Args>,
+) {
+    if keys.just_pressed(FREE_CAMERA_KEY) {
+        *target = CameraTarget::Free;
+    }
+    for (handle, key) in FOLLOW_KEYS.into_iter().enumerate().take(args.players) {
+        if keys.just_pressed(key) {
+            *target = CameraTarget::Player(handle);
+        }
+    }
+}
+
+fn move_free_camera(
+    keys: Res<ButtonInput<KeyCode>>,
+    target: Res<CameraTarget>,
+    mut camera: Query<&mut Transform, With<Camera>>,
+    time: Res<Time>,
+) {
+    if *target != CameraTarget::Free {
+        return;
+    }
+
+    let mut direction = Vec2::ZERO;
+    if keys.any_pressed([KeyCode::ArrowUp, KeyCode::KeyW]) {
+        direction.y += 1.;
+    }
+    if keys.any_pressed([KeyCode::ArrowDown, KeyCode::KeyS]) {
+        direction.y -= 1.;
+    }
+    if keys.any_pressed([KeyCode::ArrowRight, KeyCode::KeyD]) {
+        direction.x += 1.;
+    }
+    if keys.any_pressed([KeyCode::ArrowLeft, KeyCode::KeyA]) {
+        direction.x -= 1.;
+    }
+
+    let delta = direction.normalize_or_zero() * FREE_CAMERA_SPEED * time.delta_secs();
+    for mut transform in &mut camera {
+        transform.translation += delta.extend(0.);
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt::Write;
+
+use bevy::prelude::*;
+use bevy_ggrs::{ggrs::NetworkStats, *};
+
+use crate::{args::Args, Config, GameState};
+
+const TOGGLE_KEY: KeyCode = KeyCode::F3;
+
+#[derive(Component)]
+struct NetworkStatsText;
+
+/// Whether the overlay is shown. Kept from one match to the next.
+#[derive(Resource, Default)]
+struct ShowNetworkStats(bool);
+
+/// How often the session rolled back during the last second.
+#[derive(Resource)]
+struct Rollbacks {
+    this_second: u32,
+    per_second: u32,
+    timer: Timer,
+}
+
+impl Default for Rollbacks {
+    fn default() -> Self {
+        Self {
+            this_second: 0,
+            per_second: 0,
+            timer: Timer::from_seconds(1., TimerMode::Repeating),
+        }
+    }
+}
+
+/// An overlay with what GGRS knows about the connection to every peer,
+/// toggled with F3.
+pub struct NetworkStatsPlugin;
+
+impl Plugin for NetworkStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowNetworkStats>()
+            .add_systems(OnEnter(GameState::InGame), setup_network_stats_text)
+            .add_systems(LoadWorld, count_rollback)
+            .add_systems(
+                Update,
+                (toggle_network_stats, update_network_stats_text)
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            );
+    }
+}
+
+fn setup_network_stats_text(mut commands: Commands, show: Res<ShowNetworkStats>) {
+    commands.insert_resource(Rollbacks::default());
+    commands.spawn((
+        NetworkStatsText,
+        StateScoped(GameState::InGame),
+        Text::new(""),
+        TextFont {
+            font_size: 18.,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.),
+            left: Val::Px(10.),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.6)),
+        if show.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        },
+    ));
+}
+
+fn count_rollback(rollbacks: Option<ResMut<Rollbacks>>) {
+    if let Some(mut rollbacks) = rollbacks {
+        rollbacks.this_second += 1;
+    }
+}
+
+fn toggle_network_stats(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut show: ResMut<ShowNetworkStats>,
+    mut texts: Query<&mut Visibility, With<NetworkStatsText>>,
+) {
+    if !keys.just_pressed(TOGGLE_KEY) {
+        return;
+    }
+
+    show.0 = !show.0;
+    for mut visibility in &mut texts {
+        *visibility = if show.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_network_stats_text(
+    show: Res<ShowNetworkStats>,
+    session: Option<Res<Session<Config>>>,
+    mut rollbacks: ResMut<Rollbacks>,
+    args: Res<Args>,
+    time: Res<Time>,
+    mut texts: Query<&mut Text, With<NetworkStatsText>>,
+) {
+    if rollbacks.timer.tick(time.delta()).just_finished() {
+        rollbacks.per_second = std::mem::take(&mut rollbacks.this_second);
+    }
+    if !show.0 {
+        return;
+    }
+
+    let mut message = format!(
+        "Input delay: {} frames\nRollbacks: {}/s",
+        args.input_delay, rollbacks.per_second
+    );
+    match session.as_deref() {
+        Some(Session::P2P(session)) => {
+            let _ = write!(message, "\nFrames ahead: {}", session.frames_ahead());
+            for handle in session.remote_player_handles() {
+                let stats = session.network_stats(handle).ok();
+                let _ = write!(message, "\nP{}: {}", handle + 1, describe(stats));
+            }
+        }
+        Some(Session::Spectator(session)) => {
+            let _ = write!(
+                message,
+                "\nFrames behind host: {}\nHost: {}",
+                session.frames_behind_host(),
+                describe(session.network_stats().ok())
+            );
+        }
+        Some(Session::SyncTest(_)) => message.push_str("\nNo network, every player is local"),
+        None => {}
+    }
+
+    for mut text in &mut texts {
+        text.0.clone_from(&message);
+    }
+}
+
+/// Stats are only available once the peers are synchronized.
+fn describe(stats: Option<NetworkStats>) -> String {
+    match stats {
+        Some(stats) => format!(
+            "ping {} ms, send queue {}, frames behind {} local / {} remote",
+            stats.ping, stats.send_queue_len, stats.local_frames_behind, stats.remote_frames_behind
+        ),
+        None => "waiting for stats...".to_string(),
+    }
+}
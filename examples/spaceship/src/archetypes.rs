@@ -0,0 +1,148 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    ecs::system::SystemParam,
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::asset_loader::GameAssets;
+
+/// Tuning values for every kind of entity in the game, read from a
+/// `.archetypes.ron` file so they can be tweaked without a rebuild.
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct Archetypes {
+    pub spaceship: SpaceshipArchetype,
+    pub asteroid: AsteroidArchetype,
+    pub missile: MissileArchetype,
+    pub weapons: WeaponsArchetype,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpaceshipArchetype {
+    pub model: String,
+    #[serde(skip)]
+    pub scene: Handle<Scene>,
+    pub radius: f32,
+    pub speed: f32,
+    pub rotation_speed: f32,
+    pub roll_speed: f32,
+    pub health: f32,
+    pub damage: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AsteroidArchetype {
+    pub model: String,
+    #[serde(skip)]
+    pub scene: Handle<Scene>,
+    pub radius: f32,
+    pub speed: f32,
+    pub acceleration: f32,
+    pub rotation_speed: f32,
+    pub spawn_range_x: Range<f32>,
+    pub spawn_range_z: Range<f32>,
+    pub spawn_time_seconds: f32,
+    pub health: f32,
+    pub damage: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MissileArchetype {
+    pub model: String,
+    #[serde(skip)]
+    pub scene: Handle<Scene>,
+    pub radius: f32,
+    pub speed: f32,
+    pub forward_spawn_scalar: f32,
+    pub health: f32,
+    pub damage: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WeaponsArchetype {
+    pub homing: HomingArchetype,
+    pub spread: SpreadArchetype,
+    pub beam: BeamArchetype,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HomingArchetype {
+    /// Half-angle in degrees of the cone in which targets are picked.
+    pub cone_angle: f32,
+    /// Radians per second.
+    pub turn_rate: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpreadArchetype {
+    pub count: u32,
+    /// Angle in degrees between the outermost missiles.
+    pub angle: f32,
+    pub fire_interval: f32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BeamArchetype {
+    pub charge_seconds: f32,
+    pub range: f32,
+    /// Damage of a fully charged beam.
+    pub damage: f32,
+}
+
+#[derive(Default)]
+pub struct ArchetypesLoader;
+
+#[derive(Debug, Error)]
+pub enum ArchetypesLoaderError {
+    #[error("could not read archetypes: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse archetypes: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for ArchetypesLoader {
+    type Asset = Archetypes;
+    type Settings = ();
+    type Error = ArchetypesLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut archetypes = ron::de::from_bytes::<Archetypes>(&bytes)?;
+
+            // Models are dependencies, so they are reloaded along with the file.
+            archetypes.spaceship.scene = load_context.load(&archetypes.spaceship.model);
+            archetypes.asteroid.scene = load_context.load(&archetypes.asteroid.model);
+            archetypes.missile.scene = load_context.load(&archetypes.missile.model);
+
+            Ok(archetypes)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["archetypes.ron"]
+    }
+}
+
+/// Read access to the currently loaded [`Archetypes`], if any.
+#[derive(SystemParam)]
+pub struct CurrentArchetypes<'w> {
+    game_assets: Res<'w, GameAssets>,
+    archetypes: Res<'w, Assets<Archetypes>>,
+}
+
+impl CurrentArchetypes<'_> {
+    pub fn get(&self) -> Option<&Archetypes> {
+        self.archetypes.get(&self.game_assets.archetypes)
+    }
+}
@@ -1,25 +1,25 @@
 use bevy::prelude::*;
 
+use crate::archetypes::{Archetypes, ArchetypesLoader};
+
 #[derive(Resource, Debug, Default)]
-pub struct SceneAssets {
-    pub asteroid: Handle<Scene>,
-    pub spaceship: Handle<Scene>,
-    pub missiles: Handle<Scene>,
+pub struct GameAssets {
+    pub archetypes: Handle<Archetypes>,
 }
 
 pub struct AssetLoaderPlugin;
 
 impl Plugin for AssetLoaderPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<SceneAssets>()
+        app.init_asset::<Archetypes>()
+            .init_asset_loader::<ArchetypesLoader>()
+            .init_resource::<GameAssets>()
             .add_systems(Startup, load_assets);
     }
 }
 
-fn load_assets(mut scene_assets: ResMut<SceneAssets>, asset_server: Res<AssetServer>) {
-    *scene_assets = SceneAssets {
-        asteroid: asset_server.load("Asteroid.glb#Scene0"),
-        spaceship: asset_server.load("Spaceship.glb#Scene0"),
-        missiles: asset_server.load("Missiles.glb#Scene0"),
+fn load_assets(mut game_assets: ResMut<GameAssets>, asset_server: Res<AssetServer>) {
+    *game_assets = GameAssets {
+        archetypes: asset_server.load("spaceship.archetypes.ron"),
     }
 }
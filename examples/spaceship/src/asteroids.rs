@@ -1,22 +1,16 @@
-use std::ops::Range;
+use std::time::Duration;
 
 use bevy::prelude::*;
 use rand::Rng;
 
 use crate::{
-    asset_loader::SceneAssets,
-    collision_detection::Collider,
+    archetypes::CurrentArchetypes,
+    collision_detection::{Collider, CollisionDamage},
+    health::Health,
     movement::{Acceleration, MovingObjectBundle, Velocity},
+    scene_collider::{BoundingShape, ColliderFromScene},
 };
 
-const VELOCITY_SCALAR: f32 = 5.;
-const ACCELERATION_SCALAR: f32 = 1.;
-const SPAWN_RANGE_X: Range<f32> = -25. ..25.;
-const SPAWN_RANGE_Z: Range<f32> = -0. ..25.;
-const SPAWN_TIME_SECONDS: f32 = 1.;
-const ROTATE_SPEED: f32 = 2.5;
-const RADIUS: f32 = 2.5;
-
 #[derive(Component, Debug)]
 pub struct Asteroid;
 
@@ -30,12 +24,9 @@ pub struct AsteroidPlugin;
 impl Plugin for AsteroidPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SpawnTimer {
-            timer: Timer::from_seconds(SPAWN_TIME_SECONDS, TimerMode::Repeating),
+            timer: Timer::from_seconds(1., TimerMode::Repeating),
         })
-        .add_systems(
-            Update,
-            (spawn_asteroid, rotate_asteroids, handle_asteroid_collisions),
-        );
+        .add_systems(Update, (spawn_asteroid, rotate_asteroids));
     }
 }
 
@@ -43,8 +34,17 @@ fn spawn_asteroid(
     mut commands: Commands,
     mut spawn_timer: ResMut<SpawnTimer>,
     time: Res<Time>,
-    scene_assets: Res<SceneAssets>,
+    archetypes: CurrentArchetypes,
 ) {
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+    let asteroid = &archetypes.asteroid;
+
+    // Picks up a reloaded spawn time without restarting the timer.
+    spawn_timer
+        .timer
+        .set_duration(Duration::from_secs_f32(asteroid.spawn_time_seconds));
     spawn_timer.timer.tick(time.delta());
     if !spawn_timer.timer.just_finished() {
         return;
@@ -53,49 +53,44 @@ fn spawn_asteroid(
     let mut rng = rand::thread_rng();
 
     let translation = Vec3::new(
-        rng.gen_range(SPAWN_RANGE_X),
+        rng.gen_range(asteroid.spawn_range_x.clone()),
         0.,
-        rng.gen_range(SPAWN_RANGE_Z),
+        rng.gen_range(asteroid.spawn_range_z.clone()),
     );
 
     let mut random_unit_vector =
         || Vec3::new(rng.gen_range(-1. ..1.), 0., rng.gen_range(-1. ..1.)).normalize();
-    let velocity = random_unit_vector() * VELOCITY_SCALAR;
-    let acceleration = random_unit_vector() * ACCELERATION_SCALAR;
+    let velocity = random_unit_vector() * asteroid.speed;
+    let acceleration = random_unit_vector() * asteroid.acceleration;
 
     commands.spawn((
         MovingObjectBundle {
             velocity: Velocity::new(velocity),
             acceleration: Acceleration::new(acceleration),
-            collider: Collider::new(RADIUS),
+            collider: Collider::new(asteroid.radius),
             model: SceneBundle {
-                scene: scene_assets.asteroid.clone(),
+                scene: asteroid.scene.clone(),
                 transform: Transform::from_translation(translation),
                 ..default()
             },
         },
         Asteroid,
+        ColliderFromScene::new(BoundingShape::Sphere),
+        Health::new(asteroid.health),
+        CollisionDamage::new(asteroid.damage),
     ));
 }
 
-fn rotate_asteroids(mut query: Query<&mut Transform, With<Asteroid>>, time: Res<Time>) {
-    for mut transform in query.iter_mut() {
-        transform.rotate_local_z(ROTATE_SPEED * time.delta_seconds());
-    }
-}
-
-fn handle_asteroid_collisions(
-    mut commands: Commands,
-    query: Query<(Entity, &Collider), With<Asteroid>>,
+fn rotate_asteroids(
+    mut query: Query<&mut Transform, With<Asteroid>>,
+    time: Res<Time>,
+    archetypes: CurrentArchetypes,
 ) {
-    for (entity, collider) in query.iter() {
-        for &collided_entity in collider.colliding_entities.iter() {
-            // Asteroid collided with another asteroid.
-            if query.get(collided_entity).is_ok() {
-                continue;
-            }
-            // Despawn the asteroid.
-            commands.entity(entity).despawn_recursive();
-        }
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+
+    for mut transform in query.iter_mut() {
+        transform.rotate_local_z(archetypes.asteroid.rotation_speed * time.delta_seconds());
     }
 }
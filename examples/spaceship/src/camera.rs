@@ -1,18 +1,118 @@
-use bevy::prelude::*;
+use bevy::{
+    prelude::*,
+    render::camera::{ClearColorConfig, Viewport},
+    window::PrimaryWindow,
+};
+
+use crate::{
+    player::{Player, PlayerSettings},
+    spaceship::Spaceship,
+};
 
 const CAMERA_DISTANCE: f32 = 80.;
 
+/// The camera rendering the view of the player with the same index.
+#[derive(Component, Debug)]
+pub struct PlayerCamera {
+    pub player: usize,
+}
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_camera);
+        app.add_systems(Startup, spawn_camera).add_systems(
+            Update,
+            (set_split_viewports, follow_players).run_if(split_screen),
+        );
     }
 }
 
-fn spawn_camera(mut commands: Commands) {
-    commands.spawn(Camera3dBundle {
-        transform: Transform::from_xyz(0., CAMERA_DISTANCE, 0.).looking_at(Vec3::ZERO, Vec3::Z),
-        ..default()
-    });
+fn spawn_camera(mut commands: Commands, player_settings: Res<PlayerSettings>) {
+    if player_settings.players == 1 {
+        commands.spawn((
+            Camera3dBundle {
+                transform: Transform::from_xyz(0., CAMERA_DISTANCE, 0.)
+                    .looking_at(Vec3::ZERO, Vec3::Z),
+                ..default()
+            },
+            PlayerCamera { player: 0 },
+        ));
+        return;
+    }
+
+    for player in 0..player_settings.players {
+        commands.spawn((
+            Camera3dBundle {
+                camera: Camera {
+                    order: player as isize,
+                    // Cameras sharing the window clear all of it, which would
+                    // wipe the views rendered before them.
+                    clear_color: if player == 0 {
+                        ClearColorConfig::default()
+                    } else {
+                        ClearColorConfig::None
+                    },
+                    ..default()
+                },
+                transform: Transform::from_xyz(0., CAMERA_DISTANCE, 0.)
+                    .looking_at(Vec3::ZERO, Vec3::Z),
+                ..default()
+            },
+            PlayerCamera { player },
+        ));
+    }
+}
+
+fn split_screen(player_settings: Res<PlayerSettings>) -> bool {
+    player_settings.players > 1
+}
+
+fn set_split_viewports(
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&mut Camera, &PlayerCamera)>,
+    player_settings: Res<PlayerSettings>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let players = player_settings.players as u32;
+    let size = UVec2::new(window.physical_width() / players, window.physical_height());
+    // A minimized window has no room for the viewports.
+    if size.x == 0 || size.y == 0 {
+        return;
+    }
+
+    for (mut camera, player_camera) in camera_query.iter_mut() {
+        let position = UVec2::new(size.x * player_camera.player as u32, 0);
+        let is_current = camera.viewport.as_ref().is_some_and(|viewport| {
+            viewport.physical_position == position && viewport.physical_size == size
+        });
+        // Avoid touching the camera every frame.
+        if is_current {
+            continue;
+        }
+
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..default()
+        });
+    }
+}
+
+fn follow_players(
+    player_query: Query<(&Player, &Transform), With<Spaceship>>,
+    // Split-screen cameras follow their player instead of staying fixed.
+    mut camera_query: Query<(&PlayerCamera, &mut Transform), Without<Spaceship>>,
+) {
+    for (player, player_transform) in player_query.iter() {
+        for (player_camera, mut transform) in camera_query.iter_mut() {
+            if player_camera.player != player.index {
+                continue;
+            }
+            transform.translation.x = player_transform.translation.x;
+            transform.translation.z = player_transform.translation.z;
+        }
+    }
 }
@@ -1,8 +1,78 @@
 use bevy::{prelude::*, utils::HashMap};
 
+use crate::{
+    asteroids::Asteroid,
+    health::{DeathEvent, Health},
+    player::PlayerSettings,
+    spaceship::{Spaceship, SpaceshipMissile},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColliderShape {
+    Sphere { center: Vec3, radius: f32 },
+    Capsule { start: Vec3, end: Vec3, radius: f32 },
+}
+
+impl ColliderShape {
+    /// Distance from the local origin to the farthest point of the shape.
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            ColliderShape::Sphere { center, radius } => center.length() + radius,
+            ColliderShape::Capsule { start, end, radius } => {
+                start.length().max(end.length()) + radius
+            }
+        }
+    }
+
+    /// Moves the shape from the collider's local space into world space.
+    /// `scale` is the largest scale of the transform, radii grow with it.
+    pub fn transformed(&self, transform: &GlobalTransform, scale: f32) -> Self {
+        match *self {
+            ColliderShape::Sphere { center, radius } => ColliderShape::Sphere {
+                center: transform.transform_point(center),
+                radius: radius * scale,
+            },
+            ColliderShape::Capsule { start, end, radius } => ColliderShape::Capsule {
+                start: transform.transform_point(start),
+                end: transform.transform_point(end),
+                radius: radius * scale,
+            },
+        }
+    }
+
+    /// Both shapes are expected to be in the same space.
+    pub fn intersects(&self, other: &ColliderShape) -> bool {
+        let (a_start, a_end, a_radius) = self.segment();
+        let (b_start, b_end, b_radius) = other.segment();
+        let radius = a_radius + b_radius;
+
+        segment_distance_squared(a_start, a_end, b_start, b_end) < radius * radius
+    }
+
+    /// Distance along the ray to the point where it passes the shape closest,
+    /// or `None` if the ray misses. Both are expected to be in the same space.
+    pub fn ray_hit(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Option<f32> {
+        let (start, end, radius) = self.segment();
+        let ray_end = origin + direction * max_distance;
+        let (s, t) = closest_segment_params(origin, ray_end, start, end);
+        let distance_squared = origin.lerp(ray_end, s).distance_squared(start.lerp(end, t));
+
+        (distance_squared < radius * radius).then_some(s * max_distance)
+    }
+
+    // A sphere is a capsule whose segment has zero length.
+    fn segment(&self) -> (Vec3, Vec3, f32) {
+        match *self {
+            ColliderShape::Sphere { center, radius } => (center, center, radius),
+            ColliderShape::Capsule { start, end, radius } => (start, end, radius),
+        }
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Collider {
     pub radius: f32,
+    pub shapes: Vec<ColliderShape>,
     pub colliding_entities: Vec<Entity>,
 }
 
@@ -10,34 +80,113 @@ impl Collider {
     pub fn new(radius: f32) -> Self {
         Self {
             radius,
+            shapes: vec![ColliderShape::Sphere {
+                center: Vec3::ZERO,
+                radius,
+            }],
             colliding_entities: vec![],
         }
     }
+
+    /// The collider's shapes, moved into world space.
+    pub fn world_shapes<'a>(
+        &'a self,
+        transform: &'a GlobalTransform,
+    ) -> impl Iterator<Item = ColliderShape> + 'a {
+        let scale = max_scale(transform);
+        self.shapes
+            .iter()
+            .map(move |shape| shape.transformed(transform, scale))
+    }
+
+    pub fn set_shapes(&mut self, shapes: Vec<ColliderShape>) {
+        self.radius = shapes
+            .iter()
+            .map(ColliderShape::bounding_radius)
+            .fold(0., f32::max);
+        self.shapes = shapes;
+    }
+}
+
+/// Health taken from anything this entity collides with.
+#[derive(Component, Debug)]
+pub struct CollisionDamage {
+    pub amount: f32,
+}
+
+impl CollisionDamage {
+    pub fn new(amount: f32) -> Self {
+        Self { amount }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct CollisionEvent {
+    pub entity: Entity,
+    pub collided_entity: Entity,
+}
+
+impl CollisionEvent {
+    pub fn new(entity: Entity, collided_entity: Entity) -> Self {
+        Self {
+            entity,
+            collided_entity,
+        }
+    }
 }
 
 pub struct CollisionsDetectionPlugin;
 
 impl Plugin for CollisionsDetectionPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, collision_detection);
+        app.add_event::<CollisionEvent>()
+            .add_event::<DeathEvent>()
+            .add_systems(
+                Update,
+                (
+                    collision_detection,
+                    (
+                        handle_collisions::<Asteroid>,
+                        handle_collisions::<Spaceship>,
+                        handle_collisions::<SpaceshipMissile>,
+                    ),
+                    apply_collision_damage,
+                )
+                    .chain(),
+            );
     }
 }
 
 fn collision_detection(mut query: Query<(Entity, &GlobalTransform, &mut Collider)>) {
     let mut colliding_entities: HashMap<Entity, Vec<Entity>> = HashMap::new();
 
+    // Every collider is moved into world space once, instead of once per pair.
+    let colliders: Vec<_> = query
+        .iter()
+        .map(|(entity, transform, collider)| {
+            let radius = collider.radius * max_scale(transform);
+            let shapes: Vec<_> = collider.world_shapes(transform).collect();
+            (entity, transform.translation(), radius, shapes)
+        })
+        .collect();
+
     // First phase: Detect collisions
-    for (entity_a, transform_a, collider_a) in query.iter() {
-        for (entity_b, transform_b, collider_b) in query.iter() {
+    for (entity_a, position_a, radius_a, shapes_a) in colliders.iter() {
+        for (entity_b, position_b, radius_b, shapes_b) in colliders.iter() {
             if entity_a != entity_b {
-                let distance = transform_a
-                    .translation()
-                    .distance(transform_b.translation());
-                if distance < collider_a.radius + collider_b.radius {
+                // Bounding spheres don't overlap, no need to test the shapes.
+                if position_a.distance(*position_b) >= radius_a + radius_b {
+                    continue;
+                }
+
+                let shapes_intersect = shapes_a
+                    .iter()
+                    .any(|shape_a| shapes_b.iter().any(|shape_b| shape_a.intersects(shape_b)));
+                if shapes_intersect {
                     colliding_entities
-                        .entry(entity_a)
+                        .entry(*entity_a)
                         .or_insert_with(Vec::new)
-                        .push(entity_b);
+                        .push(*entity_b);
                 }
             }
         }
@@ -53,3 +202,246 @@ fn collision_detection(mut query: Query<(Entity, &GlobalTransform, &mut Collider
         }
     }
 }
+
+fn handle_collisions<T: Component>(
+    mut collision_event_writer: EventWriter<CollisionEvent>,
+    query: Query<(Entity, &Collider), With<T>>,
+) {
+    for (entity, collider) in query.iter() {
+        for &collided_entity in collider.colliding_entities.iter() {
+            // Entities of the same kind don't hurt each other.
+            if query.get(collided_entity).is_ok() {
+                continue;
+            }
+            collision_event_writer.send(CollisionEvent::new(entity, collided_entity));
+        }
+    }
+}
+
+fn apply_collision_damage(
+    mut collision_event_reader: EventReader<CollisionEvent>,
+    mut death_event_writer: EventWriter<DeathEvent>,
+    mut health_query: Query<&mut Health>,
+    collision_damage_query: Query<&CollisionDamage>,
+    missile_query: Query<&SpaceshipMissile>,
+    spaceship_query: Query<(), With<Spaceship>>,
+    player_settings: Res<PlayerSettings>,
+) {
+    for &CollisionEvent {
+        entity,
+        collided_entity,
+    } in collision_event_reader.read()
+    {
+        if is_friendly_fire(
+            entity,
+            collided_entity,
+            &missile_query,
+            &spaceship_query,
+            &player_settings,
+        ) {
+            continue;
+        }
+        let Ok(mut health) = health_query.get_mut(entity) else {
+            continue;
+        };
+        let Ok(collision_damage) = collision_damage_query.get(collided_entity) else {
+            continue;
+        };
+        if health.take_damage(collision_damage.amount) {
+            let killed_by = missile_query
+                .get(collided_entity)
+                .map_or(collided_entity, |missile| missile.owner);
+            death_event_writer.send(DeathEvent::new(entity, killed_by));
+        }
+    }
+}
+
+/// Missiles never hurt the spaceship that fired them, and only hurt other
+/// players when friendly fire is enabled.
+fn is_friendly_fire(
+    entity: Entity,
+    collided_entity: Entity,
+    missile_query: &Query<&SpaceshipMissile>,
+    spaceship_query: &Query<(), With<Spaceship>>,
+    player_settings: &PlayerSettings,
+) -> bool {
+    let (missile, spaceship) = if spaceship_query.contains(collided_entity) {
+        (entity, collided_entity)
+    } else {
+        (collided_entity, entity)
+    };
+    let Ok(missile) = missile_query.get(missile) else {
+        return false;
+    };
+    if !spaceship_query.contains(spaceship) {
+        return false;
+    }
+
+    missile.owner == spaceship || !player_settings.friendly_fire
+}
+
+fn max_scale(transform: &GlobalTransform) -> f32 {
+    transform.compute_transform().scale.max_element()
+}
+
+/// Squared distance between the closest points of segments `p1..q1` and `p2..q2`.
+fn segment_distance_squared(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> f32 {
+    let (s, t) = closest_segment_params(p1, q1, p2, q2);
+    p1.lerp(q1, s).distance_squared(p2.lerp(q2, t))
+}
+
+/// Where the closest points of segments `p1..q1` and `p2..q2` lie,
+/// as fractions along each segment.
+fn closest_segment_params(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (f32, f32) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+
+    if a <= f32::EPSILON && e <= f32::EPSILON {
+        // Both segments are points.
+        (0., 0.)
+    } else if a <= f32::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            // Parallel segments pick an arbitrary point on the first one.
+            let mut s = if denom > f32::EPSILON {
+                ((b * f - c * e) / denom).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let mut t = (b * s + f) / e;
+            if t < 0. {
+                t = 0.;
+                s = (-c / a).clamp(0., 1.);
+            } else if t > 1. {
+                t = 1.;
+                s = ((b - c) / a).clamp(0., 1.);
+            }
+            (s, t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere(center: Vec3, radius: f32) -> ColliderShape {
+        ColliderShape::Sphere { center, radius }
+    }
+
+    fn capsule(start: Vec3, end: Vec3, radius: f32) -> ColliderShape {
+        ColliderShape::Capsule { start, end, radius }
+    }
+
+    #[test]
+    fn crossing_segments_meet_in_the_middle() {
+        let (s, t) = closest_segment_params(-Vec3::X, Vec3::X, -Vec3::Z, Vec3::Z);
+        assert_eq!((s, t), (0.5, 0.5));
+        assert_eq!(
+            segment_distance_squared(
+                -Vec3::X,
+                Vec3::X,
+                Vec3::new(0., 2., -1.),
+                Vec3::new(0., 2., 1.)
+            ),
+            4.
+        );
+    }
+
+    #[test]
+    fn parallel_segments_keep_their_distance() {
+        let offset = Vec3::new(0., 0., 3.);
+        // Overlapping, side by side.
+        assert_eq!(
+            segment_distance_squared(
+                Vec3::ZERO,
+                Vec3::X * 4.,
+                Vec3::X + offset,
+                Vec3::X * 2. + offset
+            ),
+            9.
+        );
+        // One after the other, the closest points are the facing ends.
+        assert_eq!(
+            segment_distance_squared(Vec3::ZERO, Vec3::X, Vec3::X * 3., Vec3::X * 5.),
+            4.
+        );
+    }
+
+    #[test]
+    fn points_are_zero_length_segments() {
+        assert_eq!(
+            closest_segment_params(Vec3::ONE, Vec3::ONE, Vec3::ZERO, Vec3::ZERO),
+            (0., 0.)
+        );
+        // A point projects onto the segment, clamped to its ends.
+        assert_eq!(
+            closest_segment_params(
+                Vec3::new(1., 5., 0.),
+                Vec3::new(1., 5., 0.),
+                Vec3::ZERO,
+                Vec3::X * 4.
+            ),
+            (0., 0.25)
+        );
+        assert_eq!(
+            closest_segment_params(Vec3::ZERO, Vec3::X * 4., Vec3::X * 6., Vec3::X * 6.),
+            (1., 0.)
+        );
+    }
+
+    #[test]
+    fn capsules_collide_along_their_length() {
+        let ship = capsule(Vec3::new(0., 0., -2.), Vec3::new(0., 0., 2.), 0.5);
+
+        assert!(ship.intersects(&sphere(Vec3::new(1., 0., 1.5), 0.6)));
+        assert!(!ship.intersects(&sphere(Vec3::new(1., 0., 3.), 0.6)));
+        // A capsule of zero length is a sphere.
+        let point_capsule = capsule(Vec3::X, Vec3::X, 1.);
+        assert_eq!(
+            point_capsule.intersects(&ship),
+            sphere(Vec3::X, 1.).intersects(&ship)
+        );
+        assert!(point_capsule.intersects(&ship));
+    }
+
+    #[test]
+    fn rays_hit_the_closest_pass() {
+        let target = sphere(Vec3::new(0., 0., 10.), 1.);
+
+        let distance = target.ray_hit(Vec3::ZERO, Vec3::Z, 20.).unwrap();
+        assert!((distance - 10.).abs() < 1e-4);
+        assert_eq!(target.ray_hit(Vec3::ZERO, Vec3::X, 20.), None);
+        // Out of range, and behind the origin.
+        assert_eq!(target.ray_hit(Vec3::ZERO, Vec3::Z, 5.), None);
+        assert_eq!(target.ray_hit(Vec3::ZERO, -Vec3::Z, 20.), None);
+    }
+
+    #[test]
+    fn shapes_follow_the_transform() {
+        let transform = GlobalTransform::from(
+            Transform::from_xyz(10., 0., 0.).with_scale(Vec3::new(1., 1., 2.)),
+        );
+        let collider = Collider {
+            radius: 2.,
+            shapes: vec![capsule(-Vec3::Z, Vec3::Z, 0.5)],
+            colliding_entities: vec![],
+        };
+
+        let shapes: Vec<_> = collider.world_shapes(&transform).collect();
+        assert_eq!(
+            shapes,
+            [capsule(Vec3::new(10., 0., -2.), Vec3::new(10., 0., 2.), 1.)]
+        );
+    }
+}
@@ -1,13 +1,15 @@
 use bevy::prelude::*;
 
+use crate::health::Health;
+
 const DESPAWN_DISTANCE: f32 = 100.;
 
 pub struct DespawnPlugin;
 
 impl Plugin for DespawnPlugin {
     fn build(&self, app: &mut App) {
-        
-    }    
+        app.add_systems(Update, despawn_dead_entities);
+    }
 }
 
 fn despawn_far_away_entities(mut commands: Commands, query: Query<(Entity, &GlobalTransform)>) {
@@ -19,4 +21,12 @@ fn despawn_far_away_entities(mut commands: Commands, query: Query<(Entity, &Glob
             commands.entity(entity).despawn_recursive();
         }
     }
-}
\ No newline at end of file
+}
+
+fn despawn_dead_entities(mut commands: Commands, query: Query<(Entity, &Health)>) {
+    for (entity, health) in query.iter() {
+        if health.value <= 0. {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use bevy::prelude::*;
+
+#[derive(Component, Debug)]
+pub struct Health {
+    pub value: f32,
+}
+
+impl Health {
+    pub fn new(value: f32) -> Self {
+        Self { value }
+    }
+
+    /// Returns `true` if this damage is what brought the health down to zero.
+    pub fn take_damage(&mut self, amount: f32) -> bool {
+        let was_alive = self.value > 0.;
+        self.value -= amount;
+        was_alive && self.value <= 0.
+    }
+}
+
+/// Sent once when an entity's health drops to zero.
+#[derive(Event, Debug)]
+pub struct DeathEvent {
+    pub entity: Entity,
+    /// Whoever gets the credit. Missiles die in the collision too, so it's
+    /// the spaceship that fired them rather than the missile.
+    pub killed_by: Entity,
+}
+
+impl DeathEvent {
+    pub fn new(entity: Entity, killed_by: Entity) -> Self {
+        Self { entity, killed_by }
+    }
+}
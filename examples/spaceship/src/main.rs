@@ -1,11 +1,18 @@
+mod archetypes;
 mod asset_loader;
 mod asteroids;
 mod camera;
 mod collision_detection;
 mod debug;
+mod despawn;
+mod health;
 mod movement;
+mod player;
+mod radar;
+mod scene_collider;
+mod score;
 mod spaceship;
-mod despawn;
+mod weapons;
 
 use asset_loader::AssetLoaderPlugin;
 use asteroids::AsteroidPlugin;
@@ -15,11 +22,17 @@ use collision_detection::CollisionsDetectionPlugin;
 use despawn::DespawnPlugin;
 // use debug::DebugPlugin;
 use movement::MovementPlugin;
+use player::PlayerSettings;
+use radar::RadarPlugin;
+use scene_collider::SceneColliderPlugin;
+use score::ScorePlugin;
 use spaceship::SpaceshipPlugin;
+use weapons::WeaponsPlugin;
 
 fn main() {
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.1, 0., 0.15)))
+        .insert_resource(PlayerSettings::from_args())
         .insert_resource(AmbientLight {
             brightness: 700.,
             ..default()
@@ -28,12 +41,16 @@ fn main() {
             DefaultPlugins,
             AssetLoaderPlugin,
             SpaceshipPlugin,
+            WeaponsPlugin,
             AsteroidPlugin,
             MovementPlugin,
             CollisionsDetectionPlugin,
+            SceneColliderPlugin,
             // DebugPlugin,
             CameraPlugin,
             DespawnPlugin,
+            ScorePlugin,
+            RadarPlugin,
         ))
         .run();
 }
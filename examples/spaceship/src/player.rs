@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+/// Local multiplayer options, read from the command line:
+/// `--split-screen` adds a second player and `--friendly-fire` lets
+/// players' missiles hurt each other.
+#[derive(Resource, Debug, Clone)]
+pub struct PlayerSettings {
+    pub players: usize,
+    pub friendly_fire: bool,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            players: 1,
+            friendly_fire: false,
+        }
+    }
+}
+
+impl PlayerSettings {
+    pub fn from_args() -> Self {
+        let mut settings = Self::default();
+
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--split-screen" => settings.players = 2,
+                "--friendly-fire" => settings.friendly_fire = true,
+                _ => {}
+            }
+        }
+
+        settings
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Player {
+    pub index: usize,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct PlayerControls {
+    pub forward: KeyCode,
+    pub backward: KeyCode,
+    pub turn_left: KeyCode,
+    pub turn_right: KeyCode,
+    pub roll_left: KeyCode,
+    pub roll_right: KeyCode,
+    pub fire: KeyCode,
+    pub switch_weapon: KeyCode,
+}
+
+impl PlayerControls {
+    pub fn for_player(index: usize) -> Self {
+        match index {
+            0 => Self {
+                forward: KeyCode::KeyW,
+                backward: KeyCode::KeyS,
+                turn_left: KeyCode::KeyA,
+                turn_right: KeyCode::KeyD,
+                roll_left: KeyCode::ShiftLeft,
+                roll_right: KeyCode::ControlLeft,
+                fire: KeyCode::Space,
+                switch_weapon: KeyCode::KeyQ,
+            },
+            _ => Self {
+                forward: KeyCode::ArrowUp,
+                backward: KeyCode::ArrowDown,
+                turn_left: KeyCode::ArrowLeft,
+                turn_right: KeyCode::ArrowRight,
+                roll_left: KeyCode::ShiftRight,
+                roll_right: KeyCode::ControlRight,
+                fire: KeyCode::Enter,
+                switch_weapon: KeyCode::Slash,
+            },
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use bevy::{
+    prelude::*,
+    render::{camera::ClearColorConfig, view::RenderLayers},
+    window::PrimaryWindow,
+};
+
+use crate::{asteroids::Asteroid, camera::PlayerCamera, player::Player, spaceship::Spaceship};
+
+const RADAR_LAYER: u8 = 1;
+const RADAR_CAMERA_ORDER: isize = 10;
+/// World units covered by the radar, from the spaceship to its outer ring.
+const RADAR_RANGE: f32 = 150.;
+/// On-screen size of the radar in logical pixels.
+const RADAR_RADIUS: f32 = 80.;
+const RADAR_MARGIN: f32 = 20.;
+const RADAR_RINGS: usize = 3;
+const BLIP_RADIUS: f32 = 3.;
+/// How far past the edge of the view, in logical pixels, a threat still gets an indicator.
+const INDICATOR_RANGE: f32 = 400.;
+const INDICATOR_SIZE: f32 = 12.;
+
+const RING_COLOR: Color = Color::rgba(0.3, 1., 0.3, 0.5);
+const ASTEROID_COLOR: Color = Color::rgb(1., 0.3, 0.2);
+const ENEMY_COLOR: Color = Color::rgb(1., 0.9, 0.2);
+const SPACESHIP_COLOR: Color = Color::WHITE;
+
+/// Radar and edge indicators are drawn by an overlay camera that only sees this group.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct RadarGizmos;
+
+pub struct RadarPlugin;
+
+impl Plugin for RadarPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<RadarGizmos>()
+            .add_systems(Startup, (spawn_radar_camera, configure_radar_gizmos))
+            .add_systems(Update, (draw_radar, draw_edge_indicators));
+    }
+}
+
+fn spawn_radar_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: RADAR_CAMERA_ORDER,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+        RenderLayers::layer(RADAR_LAYER),
+    ));
+}
+
+fn configure_radar_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<RadarGizmos>();
+    config.render_layers = RenderLayers::layer(RADAR_LAYER);
+}
+
+fn draw_radar(
+    mut gizmos: Gizmos<RadarGizmos>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &PlayerCamera)>,
+    spaceship_query: Query<(&Player, &Transform), With<Spaceship>>,
+    asteroid_query: Query<&Transform, With<Asteroid>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    for (camera, player_camera) in camera_query.iter() {
+        let Some(viewport) = camera.logical_viewport_rect() else {
+            continue;
+        };
+        let Some((_, spaceship)) = spaceship_query
+            .iter()
+            .find(|(player, _)| player.index == player_camera.player)
+        else {
+            continue;
+        };
+
+        // Bottom right corner of the player's part of the screen.
+        let center = to_overlay(
+            window,
+            viewport.max - Vec2::splat(RADAR_RADIUS + RADAR_MARGIN),
+        );
+        for ring in 1..=RADAR_RINGS {
+            let radius = RADAR_RADIUS * ring as f32 / RADAR_RINGS as f32;
+            gizmos.circle_2d(center, radius, RING_COLOR);
+        }
+        draw_triangle(
+            &mut gizmos,
+            center,
+            Vec2::Y,
+            BLIP_RADIUS * 2.,
+            SPACESHIP_COLOR,
+        );
+
+        let heading = -*spaceship.forward();
+        let asteroids = asteroid_query
+            .iter()
+            .map(|transform| (transform.translation, ASTEROID_COLOR));
+        let enemies = spaceship_query
+            .iter()
+            .filter(|(player, _)| player.index != player_camera.player)
+            .map(|(_, transform)| (transform.translation, ENEMY_COLOR));
+
+        for (position, color) in asteroids.chain(enemies) {
+            let offset = radar_offset(position - spaceship.translation, heading);
+            if offset.length() > RADAR_RANGE {
+                continue;
+            }
+            gizmos.circle_2d(
+                center + offset * RADAR_RADIUS / RADAR_RANGE,
+                BLIP_RADIUS,
+                color,
+            );
+        }
+    }
+}
+
+fn draw_edge_indicators(
+    mut gizmos: Gizmos<RadarGizmos>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&Camera, &GlobalTransform, &PlayerCamera)>,
+    spaceship_query: Query<(&Player, &Transform), With<Spaceship>>,
+    asteroid_query: Query<&Transform, With<Asteroid>>,
+) {
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+
+    for (camera, camera_transform, player_camera) in camera_query.iter() {
+        let Some(viewport) = camera.logical_viewport_rect() else {
+            continue;
+        };
+        let asteroids = asteroid_query
+            .iter()
+            .map(|transform| (transform.translation, ASTEROID_COLOR));
+        let enemies = spaceship_query
+            .iter()
+            .filter(|(player, _)| player.index != player_camera.player)
+            .map(|(_, transform)| (transform.translation, ENEMY_COLOR));
+
+        for (position, color) in asteroids.chain(enemies) {
+            let Some(position) = camera.world_to_viewport(camera_transform, position) else {
+                continue;
+            };
+            let position = viewport.min + position;
+            // Visible threats and those far away don't need an indicator.
+            if viewport.contains(position) || !viewport.inset(INDICATOR_RANGE).contains(position) {
+                continue;
+            }
+
+            let inner = viewport.inset(-INDICATOR_SIZE);
+            let tip = position.clamp(inner.min, inner.max);
+            let direction = to_overlay(window, position) - to_overlay(window, viewport.center());
+            draw_triangle(
+                &mut gizmos,
+                to_overlay(window, tip),
+                direction.normalize_or_zero(),
+                INDICATOR_SIZE,
+                color,
+            );
+        }
+    }
+}
+
+/// Offset of a world position on the radar, in world units, rotated so the
+/// spaceship's heading points up.
+fn radar_offset(offset: Vec3, heading: Vec3) -> Vec2 {
+    // The top-down camera has world +Z up and world +X to the left.
+    let offset = Vec2::new(-offset.x, offset.z);
+    let up = Vec2::new(-heading.x, heading.z).normalize_or_zero();
+    let right = Vec2::new(up.y, -up.x);
+
+    Vec2::new(offset.dot(right), offset.dot(up))
+}
+
+/// Converts a logical window position (origin top left, Y down) into the
+/// overlay camera's world space (origin at the center, Y up).
+fn to_overlay(window: &Window, position: Vec2) -> Vec2 {
+    Vec2::new(
+        position.x - window.width() / 2.,
+        window.height() / 2. - position.y,
+    )
+}
+
+fn draw_triangle(
+    gizmos: &mut Gizmos<RadarGizmos>,
+    tip: Vec2,
+    direction: Vec2,
+    size: f32,
+    color: Color,
+) {
+    let back = tip - direction * size;
+    let side = direction.perp() * size / 2.;
+    gizmos.linestrip_2d([tip, back + side, back - side, tip], color);
+}
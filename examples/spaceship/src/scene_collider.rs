@@ -0,0 +1,253 @@
+use bevy::{math::Affine3A, prelude::*, render::primitives::Aabb, scene::SceneInstance};
+
+use crate::collision_detection::{Collider, ColliderShape};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BoundingShape {
+    #[default]
+    Sphere,
+    /// Capsule along the longest axis of the bounds.
+    Capsule,
+}
+
+/// Replaces the hand-picked radius of the entity's [`Collider`] with shapes
+/// fitted to the mesh bounds of its scene, once the scene has been spawned.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ColliderFromScene {
+    pub shape: BoundingShape,
+    /// One shape per mesh instead of a single shape around the whole model.
+    pub per_mesh: bool,
+}
+
+impl ColliderFromScene {
+    pub fn new(shape: BoundingShape) -> Self {
+        Self {
+            shape,
+            per_mesh: false,
+        }
+    }
+
+    pub fn per_mesh(shape: BoundingShape) -> Self {
+        Self {
+            shape,
+            per_mesh: true,
+        }
+    }
+}
+
+pub struct SceneColliderPlugin;
+
+impl Plugin for SceneColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, build_scene_colliders);
+    }
+}
+
+fn build_scene_colliders(
+    mut commands: Commands,
+    scene_spawner: Res<SceneSpawner>,
+    mut query: Query<(Entity, &SceneInstance, &ColliderFromScene, &mut Collider)>,
+    children_query: Query<&Children>,
+    node_query: Query<(&Transform, Option<&Aabb>)>,
+) {
+    for (entity, instance, collider_from_scene, mut collider) in query.iter_mut() {
+        if !scene_spawner.instance_is_ready(**instance) {
+            continue;
+        }
+
+        // Corners of every mesh bounding box, in the scene root's local space.
+        let mut mesh_corners = vec![];
+        collect_mesh_corners(
+            entity,
+            Affine3A::IDENTITY,
+            &children_query,
+            &node_query,
+            &mut mesh_corners,
+        );
+
+        // Bounds are calculated the frame after the meshes are spawned.
+        if mesh_corners.is_empty() {
+            continue;
+        }
+
+        let shapes = if collider_from_scene.per_mesh {
+            mesh_corners
+                .iter()
+                .map(|corners| fit_shape(collider_from_scene.shape, corners))
+                .collect()
+        } else {
+            let corners: Vec<Vec3> = mesh_corners.into_iter().flatten().collect();
+            vec![fit_shape(collider_from_scene.shape, &corners)]
+        };
+
+        collider.set_shapes(shapes);
+        commands.entity(entity).remove::<ColliderFromScene>();
+    }
+}
+
+fn collect_mesh_corners(
+    entity: Entity,
+    to_root: Affine3A,
+    children_query: &Query<&Children>,
+    node_query: &Query<(&Transform, Option<&Aabb>)>,
+    mesh_corners: &mut Vec<[Vec3; 8]>,
+) {
+    let Ok(children) = children_query.get(entity) else {
+        return;
+    };
+
+    for &child in children.iter() {
+        let Ok((transform, aabb)) = node_query.get(child) else {
+            continue;
+        };
+        let child_to_root = to_root * transform.compute_affine();
+
+        if let Some(aabb) = aabb {
+            let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+            let corners = [
+                Vec3::new(min.x, min.y, min.z),
+                Vec3::new(min.x, min.y, max.z),
+                Vec3::new(min.x, max.y, min.z),
+                Vec3::new(min.x, max.y, max.z),
+                Vec3::new(max.x, min.y, min.z),
+                Vec3::new(max.x, min.y, max.z),
+                Vec3::new(max.x, max.y, min.z),
+                Vec3::new(max.x, max.y, max.z),
+            ];
+            mesh_corners.push(corners.map(|corner| child_to_root.transform_point3(corner)));
+        }
+
+        collect_mesh_corners(
+            child,
+            child_to_root,
+            children_query,
+            node_query,
+            mesh_corners,
+        );
+    }
+}
+
+fn fit_shape(shape: BoundingShape, points: &[Vec3]) -> ColliderShape {
+    let (min, max) = points.iter().fold(
+        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+        |(min, max), &point| (min.min(point), max.max(point)),
+    );
+    let center = (min + max) / 2.;
+    let half_extents = (max - min) / 2.;
+
+    match shape {
+        BoundingShape::Sphere => ColliderShape::Sphere {
+            center,
+            radius: points
+                .iter()
+                .map(|point| point.distance(center))
+                .fold(0., f32::max),
+        },
+        BoundingShape::Capsule => {
+            let axis = if half_extents.x >= half_extents.y && half_extents.x >= half_extents.z {
+                Vec3::X
+            } else if half_extents.y >= half_extents.z {
+                Vec3::Y
+            } else {
+                Vec3::Z
+            };
+            let half_length = half_extents.dot(axis);
+            // The thicker of the two remaining axes decides the radius.
+            let radius = (half_extents - axis * half_length).max_element();
+            let offset = axis * (half_length - radius).max(0.);
+
+            ColliderShape::Capsule {
+                start: center - offset,
+                end: center + offset,
+                radius,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn box_corners(min: Vec3, max: Vec3) -> Vec<Vec3> {
+        (0..8)
+            .map(|i| {
+                Vec3::new(
+                    if i & 1 == 0 { min.x } else { max.x },
+                    if i & 2 == 0 { min.y } else { max.y },
+                    if i & 4 == 0 { min.z } else { max.z },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spheres_enclose_every_point() {
+        let points = box_corners(Vec3::new(1., -1., 0.), Vec3::new(3., 1., 4.));
+
+        let ColliderShape::Sphere { center, radius } = fit_shape(BoundingShape::Sphere, &points)
+        else {
+            panic!("expected a sphere");
+        };
+        assert_eq!(center, Vec3::new(2., 0., 2.));
+        assert!(points
+            .iter()
+            .all(|point| point.distance(center) <= radius + 1e-5));
+    }
+
+    #[test]
+    fn capsules_run_along_the_longest_axis() {
+        let points = box_corners(Vec3::new(-0.5, -0.25, -3.), Vec3::new(0.5, 0.25, 3.));
+
+        assert_eq!(
+            fit_shape(BoundingShape::Capsule, &points),
+            ColliderShape::Capsule {
+                start: Vec3::new(0., 0., -2.5),
+                end: Vec3::new(0., 0., 2.5),
+                radius: 0.5,
+            }
+        );
+    }
+
+    #[test]
+    fn degenerate_meshes_still_fit() {
+        // As wide as it's long, the capsule shrinks to a sphere.
+        assert_eq!(
+            fit_shape(BoundingShape::Capsule, &box_corners(-Vec3::ONE, Vec3::ONE)),
+            ColliderShape::Capsule {
+                start: Vec3::ZERO,
+                end: Vec3::ZERO,
+                radius: 1.,
+            }
+        );
+
+        // A flat plane keeps the width it has.
+        let plane = box_corners(Vec3::new(-4., 0., -1.), Vec3::new(4., 0., 1.));
+        assert_eq!(
+            fit_shape(BoundingShape::Capsule, &plane),
+            ColliderShape::Capsule {
+                start: Vec3::new(-3., 0., 0.),
+                end: Vec3::new(3., 0., 0.),
+                radius: 1.,
+            }
+        );
+
+        // Every vertex in the same spot.
+        let point = [Vec3::ONE; 3];
+        assert_eq!(
+            fit_shape(BoundingShape::Sphere, &point),
+            ColliderShape::Sphere {
+                center: Vec3::ONE,
+                radius: 0.,
+            }
+        );
+        assert_eq!(
+            fit_shape(BoundingShape::Capsule, &point),
+            ColliderShape::Capsule {
+                start: Vec3::ONE,
+                end: Vec3::ONE,
+                radius: 0.,
+            }
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+
+use crate::{asteroids::Asteroid, camera::PlayerCamera, health::DeathEvent, player::Player};
+
+#[derive(Component, Debug, Default)]
+pub struct Score {
+    pub value: u32,
+}
+
+#[derive(Component, Debug)]
+struct ScoreText {
+    player: usize,
+}
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_score_text, award_points, update_score_text).chain(),
+        );
+    }
+}
+
+fn spawn_score_text(
+    mut commands: Commands,
+    camera_query: Query<(Entity, &PlayerCamera), Added<PlayerCamera>>,
+) {
+    for (camera, player_camera) in camera_query.iter() {
+        commands.spawn((
+            TextBundle::from_section(
+                score_label(player_camera.player, 0),
+                TextStyle {
+                    font_size: 30.,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.),
+                left: Val::Px(10.),
+                ..default()
+            }),
+            // Each player sees their own score in their half of the screen.
+            TargetCamera(camera),
+            ScoreText {
+                player: player_camera.player,
+            },
+        ));
+    }
+}
+
+fn award_points(
+    mut death_event_reader: EventReader<DeathEvent>,
+    asteroid_query: Query<(), With<Asteroid>>,
+    mut score_query: Query<&mut Score>,
+) {
+    for death in death_event_reader.read() {
+        if !asteroid_query.contains(death.entity) {
+            continue;
+        }
+        if let Ok(mut score) = score_query.get_mut(death.killed_by) {
+            score.value += 1;
+        }
+    }
+}
+
+fn update_score_text(
+    player_query: Query<(&Player, &Score), Changed<Score>>,
+    mut text_query: Query<(&mut Text, &ScoreText)>,
+) {
+    for (player, score) in player_query.iter() {
+        for (mut text, score_text) in text_query.iter_mut() {
+            if score_text.player == player.index {
+                text.sections[0].value = score_label(player.index, score.value);
+            }
+        }
+    }
+}
+
+fn score_label(player: usize, score: u32) -> String {
+    format!("P{} score: {}", player + 1, score)
+}
@@ -1,17 +1,18 @@
 use bevy::prelude::*;
 
 use crate::{
-    asset_loader::SceneAssets, collision_detection::Collider, movement::{Acceleration, MovingObjectBundle, Velocity}
+    archetypes::CurrentArchetypes,
+    collision_detection::{Collider, CollisionDamage},
+    health::Health,
+    movement::{Acceleration, MovingObjectBundle, Velocity},
+    player::{Player, PlayerControls, PlayerSettings},
+    scene_collider::{BoundingShape, ColliderFromScene},
+    score::Score,
+    weapons::Weapon,
 };
 
 const STARTING_TRANSLATION: Vec3 = Vec3::new(0., 0., -20.);
-const SPACESHIP_SPEED: f32 = 25.;
-const SPACESHIP_ROTATION_SPEED: f32 = 2.5;
-const SPACESHIP_ROLL_SPEED: f32 = 2.5;
-const SPACESHIP_RADIUS: f32 = 5.;
-const MISSILE_SPEED: f32 = 50.;
-const MISSILE_FORWARD_SPAWN_SCALAR: f32 = 7.5;
-const MISSILE_RADIUS: f32 = 1.;
+const SPLIT_SCREEN_SPACING: f32 = 20.;
 
 #[derive(Bundle)]
 struct SpaceshipBundle {
@@ -23,94 +24,103 @@ struct SpaceshipBundle {
 pub struct Spaceship;
 
 #[derive(Component, Debug)]
-pub struct SpaceshipMissile;
+pub struct SpaceshipMissile {
+    /// The spaceship that fired the missile.
+    pub owner: Entity,
+}
 
 pub struct SpaceshipPlugin;
 
 impl Plugin for SpaceshipPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostStartup, spawn_spaceship).add_systems(
-            Update,
-            (spaceship_movement_controls, spaceship_weapon_control),
-        );
+        app.add_systems(Update, (spawn_spaceship, spaceship_movement_controls));
     }
 }
 
-fn spawn_spaceship(mut commands: Commands, scene_assets: Res<SceneAssets>) {
-    commands.spawn((
-        MovingObjectBundle {
-            velocity: Velocity::new(Vec3::ZERO),
-            acceleration: Acceleration::new(Vec3::ZERO),
-            collider: Collider::new(SPACESHIP_RADIUS),
-            model: SceneBundle {
-                scene: scene_assets.spaceship.clone(),
-                transform: Transform::from_translation(STARTING_TRANSLATION),
-                ..default()
-            },
-        },
-        Spaceship,
-    ));
-}
-
-fn spaceship_movement_controls(
-    mut query: Query<(&mut Transform, &mut Velocity), With<Spaceship>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
+fn spawn_spaceship(
+    mut commands: Commands,
+    archetypes: CurrentArchetypes,
+    player_settings: Res<PlayerSettings>,
+    mut spawned: Local<bool>,
 ) {
-    let (mut transform, mut velocity) = query.single_mut();
-    let (mut rotation, mut roll, mut movement) = (0., 0., 0.);
-
-    if keyboard_input.pressed(KeyCode::KeyD) {
-        rotation = -SPACESHIP_ROTATION_SPEED * time.delta_seconds();
-    } else if keyboard_input.pressed(KeyCode::KeyA) {
-        rotation = SPACESHIP_ROTATION_SPEED * time.delta_seconds();
-    }
-
-    if keyboard_input.pressed(KeyCode::KeyS) {
-        movement = -SPACESHIP_SPEED;
-    } else if keyboard_input.pressed(KeyCode::KeyW) {
-        movement = SPACESHIP_SPEED;
-    }
-
-    if keyboard_input.pressed(KeyCode::ShiftLeft) {
-        roll = -SPACESHIP_ROLL_SPEED * time.delta_seconds();
-    } else if keyboard_input.pressed(KeyCode::ControlLeft) {
-        roll = SPACESHIP_ROLL_SPEED * time.delta_seconds();
+    if *spawned {
+        return;
     }
+    // The spaceship is spawned as soon as its archetype has been loaded.
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+    let spaceship = &archetypes.spaceship;
 
-    // Rotate around the Y-axis.
-    transform.rotate_y(rotation);
+    for index in 0..player_settings.players {
+        // Line the players up side by side, centered on the starting point.
+        let offset =
+            (index as f32 - (player_settings.players - 1) as f32 / 2.) * SPLIT_SCREEN_SPACING;
 
-    // Rotate around the local Z-axis.
-    // The rotation is relative to the current rotation.
-    transform.rotate_local_z(roll);
-
-    // Update the spaceship's velocity based on new direction.
-    velocity.value = -transform.forward() * movement;
-}
-
-fn spaceship_weapon_control(
-    mut commands: Commands,
-    query: Query<&Transform, With<Spaceship>>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    scene_assets: Res<SceneAssets>,
-) {
-    let transform = query.single();
-    if keyboard_input.pressed(KeyCode::Space) {
         commands.spawn((
             MovingObjectBundle {
-                velocity: Velocity::new(-transform.forward() * MISSILE_SPEED),
+                velocity: Velocity::new(Vec3::ZERO),
                 acceleration: Acceleration::new(Vec3::ZERO),
-                collider: Collider::new(MISSILE_RADIUS),
+                collider: Collider::new(spaceship.radius),
                 model: SceneBundle {
-                    scene: scene_assets.missiles.clone(),
-                    transform: Transform::from_translation(
-                        transform.translation + -transform.forward() * MISSILE_FORWARD_SPAWN_SCALAR,
-                    ),
+                    scene: spaceship.scene.clone(),
+                    transform: Transform::from_translation(STARTING_TRANSLATION + Vec3::X * offset),
                     ..default()
                 },
             },
-            SpaceshipMissile,
+            Spaceship,
+            Player { index },
+            PlayerControls::for_player(index),
+            Score::default(),
+            Weapon::default(),
+            ColliderFromScene::per_mesh(BoundingShape::Capsule),
+            Health::new(spaceship.health),
+            CollisionDamage::new(spaceship.damage),
         ));
     }
+    *spawned = true;
+}
+
+fn spaceship_movement_controls(
+    mut query: Query<(&mut Transform, &mut Velocity, &PlayerControls), With<Spaceship>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    archetypes: CurrentArchetypes,
+) {
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+    let spaceship = &archetypes.spaceship;
+
+    for (mut transform, mut velocity, controls) in query.iter_mut() {
+        let (mut rotation, mut roll, mut movement) = (0., 0., 0.);
+
+        if keyboard_input.pressed(controls.turn_right) {
+            rotation = -spaceship.rotation_speed * time.delta_seconds();
+        } else if keyboard_input.pressed(controls.turn_left) {
+            rotation = spaceship.rotation_speed * time.delta_seconds();
+        }
+
+        if keyboard_input.pressed(controls.backward) {
+            movement = -spaceship.speed;
+        } else if keyboard_input.pressed(controls.forward) {
+            movement = spaceship.speed;
+        }
+
+        if keyboard_input.pressed(controls.roll_left) {
+            roll = -spaceship.roll_speed * time.delta_seconds();
+        } else if keyboard_input.pressed(controls.roll_right) {
+            roll = spaceship.roll_speed * time.delta_seconds();
+        }
+
+        // Rotate around the Y-axis.
+        transform.rotate_y(rotation);
+
+        // Rotate around the local Z-axis.
+        // The rotation is relative to the current rotation.
+        transform.rotate_local_z(roll);
+
+        // Update the spaceship's velocity based on new direction.
+        velocity.value = -transform.forward() * movement;
+    }
 }
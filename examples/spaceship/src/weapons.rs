@@ -0,0 +1,279 @@
+use bevy::prelude::*;
+
+use crate::{
+    archetypes::{CurrentArchetypes, MissileArchetype},
+    asteroids::Asteroid,
+    collision_detection::{Collider, CollisionDamage},
+    health::{DeathEvent, Health},
+    movement::{Acceleration, MovingObjectBundle, Velocity},
+    player::{Player, PlayerControls, PlayerSettings},
+    scene_collider::{BoundingShape, ColliderFromScene},
+    spaceship::{Spaceship, SpaceshipMissile},
+};
+
+const BEAM_FLASH_SECONDS: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WeaponKind {
+    #[default]
+    Missile,
+    Homing,
+    Spread,
+    Beam,
+}
+
+impl WeaponKind {
+    fn next(self) -> Self {
+        match self {
+            WeaponKind::Missile => WeaponKind::Homing,
+            WeaponKind::Homing => WeaponKind::Spread,
+            WeaponKind::Spread => WeaponKind::Beam,
+            WeaponKind::Beam => WeaponKind::Missile,
+        }
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    /// Seconds until the spread gun can fire again.
+    cooldown: f32,
+    /// Seconds the beam has been charging for.
+    charge: f32,
+}
+
+/// Missiles that steer toward the nearest asteroid in front of them.
+#[derive(Component, Debug)]
+pub struct Homing;
+
+#[derive(Component, Debug)]
+struct BeamFlash {
+    start: Vec3,
+    end: Vec3,
+    timer: Timer,
+}
+
+pub struct WeaponsPlugin;
+
+impl Plugin for WeaponsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (switch_weapons, fire_weapons).chain(),
+                steer_homing_missiles,
+                draw_beam_flashes,
+            ),
+        );
+    }
+}
+
+fn switch_weapons(
+    mut query: Query<(&Player, &PlayerControls, &mut Weapon)>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+) {
+    for (player, controls, mut weapon) in query.iter_mut() {
+        if keyboard_input.just_pressed(controls.switch_weapon) {
+            weapon.kind = weapon.kind.next();
+            weapon.charge = 0.;
+            info!("Player {} switched to {:?}.", player.index + 1, weapon.kind);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn fire_weapons(
+    mut commands: Commands,
+    mut spaceship_query: Query<(Entity, &Transform, &PlayerControls, &mut Weapon), With<Spaceship>>,
+    collider_query: Query<(Entity, &GlobalTransform, &Collider)>,
+    target_query: Query<(Has<Spaceship>, Has<SpaceshipMissile>)>,
+    mut health_query: Query<&mut Health>,
+    mut death_event_writer: EventWriter<DeathEvent>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    archetypes: CurrentArchetypes,
+    player_settings: Res<PlayerSettings>,
+) {
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+    let missile = &archetypes.missile;
+    let weapons = &archetypes.weapons;
+
+    for (entity, transform, controls, mut weapon) in spaceship_query.iter_mut() {
+        weapon.cooldown = (weapon.cooldown - time.delta_seconds()).max(0.);
+        let forward = -*transform.forward();
+        let pressed = keyboard_input.pressed(controls.fire);
+
+        match weapon.kind {
+            WeaponKind::Missile if pressed => {
+                spawn_missile(&mut commands, missile, entity, transform, forward);
+            }
+            WeaponKind::Homing if pressed => {
+                let homing_missile =
+                    spawn_missile(&mut commands, missile, entity, transform, forward);
+                commands.entity(homing_missile).insert(Homing);
+            }
+            WeaponKind::Spread if pressed && weapon.cooldown <= 0. => {
+                let spread = &weapons.spread;
+                let count = spread.count.max(1);
+                let step = if count > 1 {
+                    spread.angle / (count - 1) as f32
+                } else {
+                    0.
+                };
+
+                for i in 0..count {
+                    let angle = -spread.angle / 2. + step * i as f32;
+                    let direction = Quat::from_rotation_y(angle.to_radians()) * forward;
+                    spawn_missile(&mut commands, missile, entity, transform, direction);
+                }
+                weapon.cooldown = spread.fire_interval;
+            }
+            WeaponKind::Beam if pressed => {
+                weapon.charge =
+                    (weapon.charge + time.delta_seconds()).min(weapons.beam.charge_seconds);
+            }
+            // The beam fires when the trigger is released.
+            WeaponKind::Beam if weapon.charge > 0. => {
+                let beam = &weapons.beam;
+                let origin = transform.translation;
+
+                let hit = collider_query
+                    .iter()
+                    .filter(|&(target, ..)| {
+                        let Ok((is_spaceship, is_missile)) = target_query.get(target) else {
+                            return false;
+                        };
+                        // The beam passes through missiles, and players when friendly fire is off.
+                        target != entity
+                            && !is_missile
+                            && (player_settings.friendly_fire || !is_spaceship)
+                    })
+                    .filter_map(|(target, target_transform, collider)| {
+                        collider
+                            .world_shapes(target_transform)
+                            .filter_map(|shape| shape.ray_hit(origin, forward, beam.range))
+                            .min_by(f32::total_cmp)
+                            .map(|distance| (target, distance))
+                    })
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+                let damage = beam.damage * weapon.charge / beam.charge_seconds;
+                let length = match hit {
+                    Some((target, distance)) => {
+                        if let Ok(mut health) = health_query.get_mut(target) {
+                            if health.take_damage(damage) {
+                                death_event_writer.send(DeathEvent::new(target, entity));
+                            }
+                        }
+                        distance
+                    }
+                    None => beam.range,
+                };
+
+                commands.spawn(BeamFlash {
+                    start: origin,
+                    end: origin + forward * length,
+                    timer: Timer::from_seconds(BEAM_FLASH_SECONDS, TimerMode::Once),
+                });
+                weapon.charge = 0.;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn spawn_missile(
+    commands: &mut Commands,
+    missile: &MissileArchetype,
+    owner: Entity,
+    transform: &Transform,
+    direction: Vec3,
+) -> Entity {
+    commands
+        .spawn((
+            MovingObjectBundle {
+                velocity: Velocity::new(direction * missile.speed),
+                acceleration: Acceleration::new(Vec3::ZERO),
+                collider: Collider::new(missile.radius),
+                model: SceneBundle {
+                    scene: missile.scene.clone(),
+                    // Like the spaceships, missiles fly toward their back.
+                    transform: Transform::from_translation(
+                        transform.translation + direction * missile.forward_spawn_scalar,
+                    )
+                    .looking_to(-direction, Vec3::Y),
+                    ..default()
+                },
+            },
+            SpaceshipMissile { owner },
+            ColliderFromScene::new(BoundingShape::Capsule),
+            Health::new(missile.health),
+            CollisionDamage::new(missile.damage),
+        ))
+        .id()
+}
+
+fn steer_homing_missiles(
+    mut missile_query: Query<(&mut Transform, &mut Velocity), With<Homing>>,
+    asteroid_query: Query<&Transform, With<Asteroid>>,
+    time: Res<Time>,
+    archetypes: CurrentArchetypes,
+) {
+    let Some(archetypes) = archetypes.get() else {
+        return;
+    };
+    let homing = &archetypes.weapons.homing;
+    let cone_angle = homing.cone_angle.to_radians();
+    let max_turn = homing.turn_rate * time.delta_seconds();
+
+    for (mut transform, mut velocity) in missile_query.iter_mut() {
+        let speed = velocity.value.length();
+        if speed == 0. {
+            continue;
+        }
+        let heading = velocity.value / speed;
+
+        let nearest = asteroid_query
+            .iter()
+            .map(|asteroid_transform| asteroid_transform.translation - transform.translation)
+            .filter(|&offset| offset != Vec3::ZERO && heading.angle_between(offset) <= cone_angle)
+            .min_by(|a, b| a.length_squared().total_cmp(&b.length_squared()));
+        let Some(offset) = nearest else {
+            continue;
+        };
+
+        // Turn toward the target, at most `max_turn` radians per frame.
+        let to_target = offset.normalize();
+        let angle = heading.angle_between(to_target);
+        let new_heading = if angle <= max_turn {
+            to_target
+        } else {
+            Quat::IDENTITY.slerp(
+                Quat::from_rotation_arc(heading, to_target),
+                max_turn / angle,
+            ) * heading
+        };
+
+        velocity.value = new_heading * speed;
+        // The model turns with it, instead of flying sideways.
+        transform.look_to(-new_heading, Vec3::Y);
+    }
+}
+
+fn draw_beam_flashes(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut BeamFlash)>,
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+) {
+    for (entity, mut flash) in query.iter_mut() {
+        flash.timer.tick(time.delta());
+        if flash.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        gizmos.line(flash.start, flash.end, Color::CYAN);
+    }
+}